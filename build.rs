@@ -0,0 +1,21 @@
+fn main() {
+    // cargo tells the build script about features through the environment, the cfg only keeps
+    // cbindgen out of builds that don't have it
+    if std::env::var_os("CARGO_FEATURE_CAPI").is_some() {
+        #[cfg(feature = "capi")]
+        write_c_header();
+    }
+}
+
+#[cfg(feature = "capi")]
+fn write_c_header() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("cargo sets CARGO_MANIFEST_DIR");
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).expect("cbindgen.toml is valid"))
+        .generate()
+        .expect("the C header generates")
+        .write_to_file(format!("{}/include/blackjack_rs.h", crate_dir));
+}
@@ -0,0 +1,104 @@
+#ifndef BLACKJACK_RS_H
+#define BLACKJACK_RS_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+typedef enum BjAction {
+  BjAction_Hit = 0,
+  BjAction_Stay = 1,
+  BjAction_Double = 2,
+  BjAction_Split = 3,
+} BjAction;
+
+typedef enum BjStatus {
+  BjStatus_Ok = 0,
+  BjStatus_NullPointer = 1,
+  BjStatus_InvalidCardIndex = 2,
+  BjStatus_InvalidPosition = 3,
+  BjStatus_EmptyDeck = 4,
+  BjStatus_NotEnoughCards = 5,
+  BjStatus_CardNotAvailable = 6,
+  BjStatus_SolverError = 7,
+  BjStatus_Panic = 8,
+} BjStatus;
+
+typedef struct BjPosition BjPosition;
+
+typedef struct BjActionEvs {
+  double hit;
+  double stay;
+  enum BjAction best_action;
+  double best_expected_value;
+  bool can_double;
+  double double_ev;
+  bool can_split;
+  double split_ev;
+} BjActionEvs;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+const char *bj_last_error_message(void);
+
+/**
+ * an undealt game from 10 counts, one per blackjack value index
+ *
+ * # Safety
+ * counts points to 10 values and out is writable.
+ */
+enum BjStatus bj_position_from_counts(const size_t *counts, struct BjPosition **out);
+
+/**
+ * a dealt position. The hand and dealer cards are removed from the 10 shoe_counts, or with
+ * counts_are_remaining the counts are taken as the deck left after they came out
+ *
+ * # Safety
+ * hand and dealer point to hand_len and dealer_len values (they may be null when the length is
+ * 0), shoe_counts points to 10 values and out is writable.
+ */
+enum BjStatus bj_position_new(const size_t *hand,
+                              size_t hand_len,
+                              const size_t *dealer,
+                              size_t dealer_len,
+                              bool stay,
+                              const size_t *shoe_counts,
+                              bool counts_are_remaining,
+                              struct BjPosition **out);
+
+/**
+ * # Safety
+ * position comes from bj_position_* and hasn't been freed, out is writable.
+ */
+enum BjStatus bj_position_action_evs(const struct BjPosition *position, struct BjActionEvs *out);
+
+/**
+ * the ev of playing the position out with the best strategy, or of the whole round if undealt
+ *
+ * # Safety
+ * position comes from bj_position_* and hasn't been freed, out is writable.
+ */
+enum BjStatus bj_position_expected_value(const struct BjPosition *position, double *out);
+
+/**
+ * writes the 10 counts left in the position's deck
+ *
+ * # Safety
+ * position comes from bj_position_* and hasn't been freed, counts has room for 10 values.
+ */
+enum BjStatus bj_position_deck_counts(const struct BjPosition *position, size_t *counts);
+
+/**
+ * null is ignored
+ *
+ * # Safety
+ * position comes from bj_position_* and is freed once.
+ */
+void bj_position_free(struct BjPosition *position);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BLACKJACK_RS_H */
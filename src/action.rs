@@ -0,0 +1,71 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Action {
+    Hit,
+    Stay,
+    Double,
+    Split,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Hit => write!(f, "hit"),
+            Action::Stay => write!(f, "stay"),
+            Action::Double => write!(f, "double"),
+            Action::Split => write!(f, "split"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActionExpectedValues {
+    pub hit: f64,
+    pub stay: f64,
+    // None when the rules don't allow doubling this hand
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub double: Option<f64>,
+    // None when the rules don't allow splitting this hand
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub split: Option<f64>,
+}
+
+impl ActionExpectedValues {
+    pub fn get_expected_value(&self, action:Action) -> f64 {
+        match action {
+            Action::Hit => self.hit,
+            Action::Stay => self.stay,
+            // not an option, so never the better choice
+            Action::Double => self.double.unwrap_or(f64::NEG_INFINITY),
+            Action::Split => self.split.unwrap_or(f64::NEG_INFINITY),
+        }
+    }
+
+    // ties go to stay, same as the solver's max(stay, hit), a double has to beat both and a split
+    // has to beat everything else
+    pub fn best_action(&self) -> Action {
+        let mut best = if self.hit > self.stay { Action::Hit } else { Action::Stay };
+        if let Some(x) = self.double {
+            if x > self.get_expected_value(best) {
+                best = Action::Double;
+            }
+        }
+        match self.split {
+            Some(x) if x > self.get_expected_value(best) => Action::Split,
+            _ => best,
+        }
+    }
+
+    pub fn get_best_expected_value(&self) -> f64 {
+        self.get_expected_value(self.best_action())
+    }
+
+    // how much ev is given up by taking action instead of the best one
+    pub fn get_expected_value_lost(&self, action:Action) -> f64 {
+        self.get_best_expected_value() - self.get_expected_value(action)
+    }
+}
@@ -0,0 +1,41 @@
+use blackjack_rs::cache_policy::FILE_CACHE_POLICY;
+use blackjack_rs::chart::StrategyChart;
+use blackjack_rs::infinite_deck::InfiniteDeckEngine;
+use blackjack_rs::opt_bj_game::OptimizedBlackJackGame;
+use blackjack_rs::ruleset::Ruleset;
+
+use std::env;
+use std::fs;
+use std::time::Instant;
+
+fn get_option(args:&[String], name:&str) -> Option<String> {
+    let position = args.iter().position(|x| x == name)?;
+    args.get(position + 1).cloned()
+}
+
+// infinite deck chart by default, --exact-decks N solves every cell on an N deck shoe instead.
+// --rules picks a ruleset for the exact chart, the infinite deck engine only plays standard rules
+fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    let ruleset = get_option(&args, "--rules").map(|x| x.parse::<Ruleset>().unwrap_or_else(|e| panic!("{}", e))).unwrap_or_default();
+    let now = Instant::now();
+    let chart = match get_option(&args, "--exact-decks") {
+        Some(x) => {
+            fs::create_dir_all("bin/").expect("could not create cache directory");
+            let num_decks:usize = x.parse().expect("--exact-decks must be a positive integer");
+            StrategyChart::from_game(&OptimizedBlackJackGame::new_with_ruleset(ruleset, num_decks).with_cache_policy(FILE_CACHE_POLICY))
+        }
+        None => {
+            if !ruleset.is_standard() {
+                panic!("--rules {} needs --exact-decks", ruleset);
+            }
+            let engine = InfiniteDeckEngine::new_standard();
+            println!("Infinite deck EV: {:+.6}", engine.get_deck_expected_value());
+            StrategyChart::from_infinite_deck(&engine)
+        }
+    };
+    chart.print_contents();
+    println!();
+    chart.print_expected_values();
+    println!("computation took {} seconds", now.elapsed().as_secs_f64());
+}
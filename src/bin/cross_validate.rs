@@ -0,0 +1,29 @@
+use blackjack_rs::validation::{cross_validate, new_small_deck_game};
+
+use std::env;
+use std::process;
+
+// benchmarks both exact engines against each other and against simulation on small decks
+fn main() {
+    let trials:usize = env::args().nth(1).map(|x| x.parse().expect("trials must be a positive integer")).unwrap_or(100000);
+    let decks:[(&str, [usize;10]);4] = [
+        ("one suit", [1, 1, 1, 1, 1, 1, 1, 1, 1, 4]),
+        ("one suit, no ace", [0, 1, 1, 1, 1, 1, 1, 1, 1, 4]),
+        ("one suit, extra tens", [1, 1, 1, 1, 1, 1, 1, 1, 1, 8]),
+        ("one deck with cards removed", [2, 1, 1, 1, 2, 2, 1, 1, 1, 4]),
+    ];
+    let mut discrepancies = 0;
+    for (name, counts) in decks.iter() {
+        println!();
+        println!("{} {:?}", name, counts);
+        let result = cross_validate(&new_small_deck_game(counts), trials);
+        result.print_contents();
+        if result.has_discrepancy(5.0) {
+            println!("DISCREPANCY");
+            discrepancies += 1;
+        }
+    }
+    if discrepancies > 0 {
+        process::exit(1);
+    }
+}
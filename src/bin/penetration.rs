@@ -0,0 +1,20 @@
+use blackjack_rs::penetration::analyse_penetration;
+use blackjack_rs::table::Strategy;
+
+use std::env;
+
+fn get_option(args:&[String], name:&str) -> Option<String> {
+    let position = args.iter().position(|x| x == name)?;
+    args.get(position + 1).cloned()
+}
+
+fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    let num_decks:usize = get_option(&args, "--decks").map(|x| x.parse().expect("--decks must be a positive integer")).unwrap_or(6);
+    let burn_cards:usize = get_option(&args, "--burn").map(|x| x.parse().expect("--burn must be a number of cards")).unwrap_or(1);
+    let rounds:usize = get_option(&args, "--rounds").map(|x| x.parse().expect("--rounds must be a positive integer")).unwrap_or(1000000);
+    let penetrations = [0.5, 0.6, 0.67, 0.75, 0.8, 0.85, 0.9];
+
+    let analysis = analyse_penetration(num_decks, burn_cards, &penetrations, Strategy::Basic, rounds);
+    analysis.print_contents();
+}
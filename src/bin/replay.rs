@@ -0,0 +1,52 @@
+use blackjack_rs::history::{read_hand_histories, replay_hand_history};
+use blackjack_rs::parallel_solver::ParallelSolver;
+
+use std::env;
+
+fn get_option(args:&[String], name:&str) -> Option<String> {
+    let position = args.iter().position(|x| x == name)?;
+    args.get(position + 1).cloned()
+}
+
+fn cards_to_string(cards:&[usize]) -> String {
+    cards.iter().map(|x| if *x == 0 { String::from("A") } else { (x + 1).to_string() }).collect::<Vec<String>>().join(" ")
+}
+
+// solves every decision in a hand history file again and reports the ev given up
+fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    let path = args.get(1).filter(|x| !x.starts_with("--")).cloned().unwrap_or_else(|| panic!("usage: replay <history.jsonl> [--threads N]"));
+    let num_threads:usize = get_option(&args, "--threads").map(|x| x.parse().expect("--threads must be a non negative integer")).unwrap_or(0);
+
+    let histories = read_hand_histories(&path).unwrap_or_else(|e| panic!("{}", e));
+    let solver = ParallelSolver::new(num_threads).unwrap_or_else(|e| panic!("{}", e));
+    let mut decisions = 0;
+    let mut errors = 0;
+    let mut ev_lost = 0.0;
+    let mut net_result = 0.0;
+    for history in histories.iter() {
+        println!("Round {}: hand {}, dealer {}, payout {:+.1}", history.round, cards_to_string(&history.hand), cards_to_string(&history.dealer), history.payout);
+        for replayed in replay_hand_history(history, &solver).unwrap_or_else(|e| panic!("{}", e)) {
+            decisions += 1;
+            if replayed.ev_lost > 0.0 {
+                errors += 1;
+                ev_lost += replayed.ev_lost;
+            }
+            println!(
+                "  {:<12} {:<4} hit {:+.4} stay {:+.4}, best is {}, ev lost {:.4}",
+                cards_to_string(&replayed.hand),
+                replayed.action,
+                replayed.action_evs.hit,
+                replayed.action_evs.stay,
+                replayed.action_evs.best_action(),
+                replayed.ev_lost,
+            );
+        }
+        net_result += history.payout;
+    }
+    println!();
+    println!("Rounds: {}", histories.len());
+    println!("Decisions: {}, errors: {}", decisions, errors);
+    println!("EV lost: {:.4} total, {:.4} per round", ev_lost, ev_lost / f64::max(histories.len() as f64, 1.0));
+    println!("Net result: {:+.1}", net_result);
+}
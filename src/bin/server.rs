@@ -0,0 +1,203 @@
+use blackjack_rs::action::{Action, ActionExpectedValues};
+use blackjack_rs::error::BlackjackError;
+use blackjack_rs::ev_cache::{EvCache, MemoryCache, RedisCache};
+use blackjack_rs::opt_bj_game::OptimizedBlackJackGame;
+use blackjack_rs::parallel_solver::ParallelSolver;
+use blackjack_rs::progress::CancellationToken;
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response};
+
+use std::env;
+use std::io::Read;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const REDIS_KEY_PREFIX:&str = "blackjack_rs:ev:";
+
+// the server is standard rules only, ParallelSolver doesn't play the other rulesets. rules is in
+// the request so they can be added later without changing it, anything else is a 400
+const STANDARD_RULES:&str = "standard";
+
+// a dealt position. Cards are blackjack value indices, deck_counts is what is left in the shoe
+// after the hand and upcard came out
+#[derive(Deserialize)]
+struct EvRequest {
+    hand: Vec<usize>,
+    upcard: usize,
+    deck_counts: [usize;10],
+    rules: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EvResponse {
+    hit: f64,
+    stay: f64,
+    best_action: Action,
+    best_expected_value: f64,
+    cached: bool,
+    // actions the solver doesn't value, best_action only picks between the others
+    not_computed: [Action;2],
+}
+
+// the parallel solver plays hit or stay only
+const NOT_COMPUTED:[Action;2] = [Action::Double, Action::Split];
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+struct EvServer {
+    solver: ParallelSolver,
+    cache: Box<dyn EvCache>,
+    timeout: Duration,
+}
+
+fn get_option(args:&[String], name:&str) -> Option<String> {
+    let position = args.iter().position(|x| x == name)?;
+    args.get(position + 1).cloned()
+}
+
+fn json_response<T: Serialize>(status:u16, body:&T) -> Response<std::io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).expect("static header is valid");
+    Response::from_string(serde_json::to_string(body).expect("responses always serialise"))
+        .with_status_code(status)
+        .with_header(header)
+}
+
+fn error_response(status:u16, message:String) -> Response<std::io::Cursor<Vec<u8>>> {
+    json_response(status, &ErrorResponse { error:message })
+}
+
+fn get_error_status(error:&BlackjackError) -> u16 {
+    match error {
+        BlackjackError::Cancelled => 503,
+        BlackjackError::InvalidPosition(_)
+        | BlackjackError::InvalidCardIndex(_)
+        | BlackjackError::CardNotAvailable { .. }
+        | BlackjackError::EmptyDeck
+        | BlackjackError::NotEnoughCards { .. } => 422,
+        _ => 500,
+    }
+}
+
+impl EvServer {
+    // cancels the solve once the timeout passes, the timer stops early when the solve finishes
+    fn solve_with_timeout(&self, position:&OptimizedBlackJackGame) -> Result<ActionExpectedValues, BlackjackError> {
+        let cancellation = CancellationToken::new();
+        let (done, finished) = mpsc::channel::<()>();
+        let timer_token = cancellation.clone();
+        let timeout = self.timeout;
+        let timer = thread::spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(timeout) {
+                timer_token.cancel();
+            }
+        });
+        let result = self.solver.try_get_action_expected_values_with_token(position, &cancellation);
+        drop(done);
+        timer.join().ok();
+        result
+    }
+
+    fn get_action_evs(&self, request:&EvRequest) -> Result<(ActionExpectedValues, bool), BlackjackError> {
+        let position = OptimizedBlackJackGame::builder()
+            .hand(&request.hand)
+            .dealer(&[request.upcard])
+            .remaining_counts(request.deck_counts)
+            .build()?;
+        let key = format!("{}:{}", STANDARD_RULES, position.unique_key());
+        // a broken cache shouldn't fail the request, the position is solved instead
+        match self.cache.get(&key) {
+            Ok(Some(action_evs)) => return Ok((action_evs, true)),
+            Ok(None) => (),
+            Err(e) => eprintln!("{}", e),
+        }
+        let action_evs = self.solve_with_timeout(&position)?;
+        if let Err(e) = self.cache.insert(&key, action_evs) {
+            eprintln!("{}", e);
+        }
+        Ok((action_evs, false))
+    }
+
+    fn handle_ev(&self, request:&mut Request) -> Response<std::io::Cursor<Vec<u8>>> {
+        let mut body = String::new();
+        if let Err(e) = request.as_reader().read_to_string(&mut body) {
+            return error_response(400, e.to_string());
+        }
+        let ev_request = match serde_json::from_str::<EvRequest>(&body) {
+            Ok(x) => x,
+            Err(e) => return error_response(400, e.to_string()),
+        };
+        let rules = ev_request.rules.as_deref().unwrap_or(STANDARD_RULES);
+        if rules != STANDARD_RULES {
+            return error_response(400, format!("unknown rules {}", rules));
+        }
+        match self.get_action_evs(&ev_request) {
+            Ok((action_evs, cached)) => json_response(200, &EvResponse {
+                hit:action_evs.hit,
+                stay:action_evs.stay,
+                best_action:action_evs.best_action(),
+                best_expected_value:action_evs.get_best_expected_value(),
+                cached:cached,
+                not_computed:NOT_COMPUTED,
+            }),
+            Err(e) => error_response(get_error_status(&e), e.to_string()),
+        }
+    }
+
+    fn handle(&self, mut request:Request) {
+        let response = match (request.method(), request.url()) {
+            (Method::Post, "/ev") => self.handle_ev(&mut request),
+            (Method::Get, "/health") => json_response(200, &"ok"),
+            _ => error_response(404, format!("no route for {} {}", request.method(), request.url())),
+        };
+        if let Err(e) = request.respond(response) {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+// POST /ev with {"hand":[..], "upcard":n, "deck_counts":[..10], "rules":"standard"} answers
+// with the hit and stay evs and the better of the two, double and split are listed as not
+// computed. --port (8080), --workers request threads (4), --threads solver threads (0 uses every
+// core), --timeout-ms per request (10000), --redis url shares solved positions through redis
+// instead of this process's memory. --memo-len caps the solver's memo (4194304 entries) and
+// --cache-len the in memory cache (1048576 answers), each is emptied when it fills up
+fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    let port:u16 = get_option(&args, "--port").map(|x| x.parse().expect("--port must be a port number")).unwrap_or(8080);
+    let num_workers:usize = get_option(&args, "--workers").map(|x| x.parse().expect("--workers must be a positive integer")).unwrap_or(4);
+    let num_threads:usize = get_option(&args, "--threads").map(|x| x.parse().expect("--threads must be a non negative integer")).unwrap_or(0);
+    let timeout_ms:u64 = get_option(&args, "--timeout-ms").map(|x| x.parse().expect("--timeout-ms must be a number of milliseconds")).unwrap_or(10000);
+    let max_memo_len:usize = get_option(&args, "--memo-len").map(|x| x.parse().expect("--memo-len must be a positive integer")).unwrap_or(1 << 22);
+    let cache_len:usize = get_option(&args, "--cache-len").map(|x| x.parse().expect("--cache-len must be a positive integer")).unwrap_or(1 << 20);
+
+    let cache:Box<dyn EvCache> = match get_option(&args, "--redis") {
+        Some(url) => Box::new(RedisCache::connect(&url, REDIS_KEY_PREFIX).unwrap_or_else(|e| panic!("{}", e))),
+        None => Box::new(MemoryCache::with_capacity(cache_len)),
+    };
+    let ev_server = Arc::new(EvServer {
+        solver:ParallelSolver::new(num_threads).unwrap_or_else(|e| panic!("{}", e)).with_max_memo_len(max_memo_len),
+        cache:cache,
+        timeout:Duration::from_millis(timeout_ms),
+    });
+    let http = Arc::new(tiny_http::Server::http(("0.0.0.0", port)).unwrap_or_else(|e| panic!("{}", e)));
+    println!("listening on port {} with {} workers", port, num_workers);
+
+    let mut workers = Vec::<thread::JoinHandle<()>>::new();
+    for _ in 0..num_workers.max(1) {
+        let http = http.clone();
+        let ev_server = ev_server.clone();
+        workers.push(thread::spawn(move || {
+            for request in http.incoming_requests() {
+                ev_server.handle(request);
+            }
+        }));
+    }
+    for worker in workers {
+        worker.join().ok();
+    }
+}
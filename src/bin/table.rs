@@ -0,0 +1,35 @@
+use blackjack_rs::counting::CountingSystem;
+use blackjack_rs::shoe::Shoe;
+use blackjack_rs::table::{BetPolicy, Seat, Strategy, Table, MAX_SEATS};
+
+use std::env;
+
+fn get_option(args:&[String], name:&str) -> Option<String> {
+    let position = args.iter().position(|x| x == name)?;
+    args.get(position + 1).cloned()
+}
+
+// runs the same counting seat at every table size and reports results by seat position
+fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    let num_decks:usize = get_option(&args, "--decks").map(|x| x.parse().expect("--decks must be a positive integer")).unwrap_or(6);
+    let penetration:f64 = get_option(&args, "--penetration").map(|x| x.parse().expect("--penetration must be a fraction")).unwrap_or(0.75);
+    let rounds:usize = get_option(&args, "--rounds").map(|x| x.parse().expect("--rounds must be a positive integer")).unwrap_or(100000);
+    let spread:f64 = get_option(&args, "--spread").map(|x| x.parse().expect("--spread must be a number of units")).unwrap_or(8.0);
+    let system_name = get_option(&args, "--system").unwrap_or(String::from("hilo"));
+    let system = CountingSystem::from_name(&system_name).unwrap_or_else(|| panic!("unknown counting system {}", system_name));
+    // every table size gets the same card stream when seeded
+    let seed:Option<u64> = get_option(&args, "--seed").map(|x| x.parse().expect("--seed must be a non negative integer"));
+
+    let seat = Seat::new(Strategy::Basic, BetPolicy::CountRamp { system:system, unit:1.0, max_units:spread });
+    for num_seats in 1..=MAX_SEATS {
+        let mut shoe = Shoe::new_cut_card(num_decks, penetration, 0);
+        if let Some(seed) = seed {
+            shoe = shoe.with_seed(seed);
+        }
+        let mut table = Table::with_shoe(shoe, vec![seat; num_seats]);
+        table.simulate(rounds);
+        println!();
+        table.print_contents();
+    }
+}
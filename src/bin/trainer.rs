@@ -0,0 +1,325 @@
+use blackjack_rs::action::Action;
+use blackjack_rs::card_source::{draw_with, CardSource, RngSource};
+use blackjack_rs::counting::CountingSystem;
+use blackjack_rs::history::{DecisionRecord, HandHistory};
+#[cfg(feature = "serde")]
+use blackjack_rs::history::HandHistoryWriter;
+use blackjack_rs::opt_bj_game::{new_standard_deck, OptimizedBlackJackGame};
+use blackjack_rs::parallel_solver::ParallelSolver;
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CARD_NAMES: [&str;10] = ["A", "2", "3", "4", "5", "6", "7", "8", "9", "10"];
+
+struct TrainerStats {
+    rounds: usize,
+    decisions: usize,
+    errors: usize,
+    ev_lost: f64,
+    net_result: f64,
+}
+
+impl TrainerStats {
+    fn new() -> Self {
+        Self {
+            rounds:0,
+            decisions:0,
+            errors:0,
+            ev_lost:0.0,
+            net_result:0.0,
+        }
+    }
+
+    fn get_error_rate(&self) -> f64 {
+        if self.decisions == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.decisions as f64
+    }
+
+    fn print_contents(&self) {
+        println!("Rounds: {}, Net result: {:+.2} units", self.rounds, self.net_result);
+        println!("Decisions: {}, Errors: {}, Error rate: {:.1}%", self.decisions, self.errors, 100.0 * self.get_error_rate());
+        println!("Total EV lost: {:.4} units", self.ev_lost);
+    }
+}
+
+fn cards_to_string(cards:&[usize]) -> String {
+    cards.iter().map(|x| CARD_NAMES[*x]).collect::<Vec<&str>>().join(" ")
+}
+
+// None means stdin was closed
+fn read_line(prompt:&str) -> Option<String> {
+    print!("{}", prompt);
+    io::stdout().flush().expect("could not flush stdout");
+    let mut line = String::new();
+    let read = io::stdin().read_line(&mut line).expect("could not read stdin");
+    if read == 0 {
+        return None;
+    }
+    Some(line.trim().to_lowercase())
+}
+
+// None means the user asked to quit (or stdin closed)
+fn read_action() -> Option<Action> {
+    loop {
+        match read_line("[h]it, [s]tay or [q]uit > ")?.as_str() {
+            "h" | "hit" => return Some(Action::Hit),
+            "s" | "stay" | "stand" => return Some(Action::Stay),
+            "q" | "quit" => return None,
+            _ => println!("unrecognised action"),
+        }
+    }
+}
+
+// plays one round, returns the table left for the next round and the round's history, or None if the user quit
+fn play_round<S: CardSource>(shoe:&OptimizedBlackJackGame, solver:&ParallelSolver, source:&mut S, stats:&mut TrainerStats) -> Option<(OptimizedBlackJackGame, HandHistory)> {
+    let mut game = shoe.deal_with(source);
+    let hand = game.get_hand().clone();
+    let mut decisions = Vec::<DecisionRecord>::new();
+    println!();
+    println!("Dealer shows: {}", cards_to_string(game.get_dealer()));
+    // naturals are settled straight after the deal, same as get_expected_value, so there is
+    // nothing to decide: a blackjack stays, and so does any hand against a dealer blackjack
+    let natural = game.is_hand_blackjack() || game.is_dealer_blackjack();
+    if natural {
+        println!("Hand: {} ({}){}", cards_to_string(game.get_hand()), game.get_hand_value(), if game.is_hand_blackjack() { ", blackjack" } else { "" });
+    }
+    while !natural && !game.is_hand_bust() {
+        println!("Hand: {} ({})", cards_to_string(game.get_hand()), game.get_hand_value());
+        let action = read_action()?;
+        let action_evs = solver.get_action_expected_values(&game);
+        let ev_lost = action_evs.get_expected_value_lost(action);
+        stats.decisions += 1;
+        if ev_lost > 0.0 {
+            stats.errors += 1;
+            stats.ev_lost += ev_lost;
+        }
+        decisions.push(DecisionRecord {
+            hand:game.get_hand().clone(),
+            action:action,
+            recommended:action_evs.best_action(),
+            hit_ev:action_evs.hit,
+            stay_ev:action_evs.stay,
+        });
+        println!(
+            "Hit EV: {:+.4}, Stay EV: {:+.4}, best is {}{}",
+            action_evs.hit,
+            action_evs.stay,
+            action_evs.best_action(),
+            if ev_lost > 0.0 { format!(", you gave up {:.4}", ev_lost) } else { String::new() },
+        );
+        match action {
+            Action::Hit => game = game.hit_with(source),
+            // read_action only offers hit and stay
+            Action::Stay | Action::Double | Action::Split => break,
+        }
+    }
+
+    let mut result = game.stay();
+    if result.is_hand_bust() {
+        println!("Hand: {} ({}), bust", cards_to_string(result.get_hand()), result.get_hand_value());
+    }
+    else {
+        // dealer stays on >=17
+        while result.get_dealer_value() < 17 {
+            result = result.dealer_hit_with(source);
+        }
+        result = result.stay();
+        println!("Dealer: {} ({})", cards_to_string(result.get_dealer()), result.get_dealer_value());
+    }
+    // the position is fully resolved, so its ev is the payout
+    let payout = result.get_expected_value();
+    println!("Result: {:+.1}", payout);
+    stats.rounds += 1;
+    stats.net_result += payout;
+    let history = HandHistory {
+        round:stats.rounds,
+        deck_counts:shoe.get_deck_counts(),
+        hand:hand,
+        dealer:result.get_dealer().clone(),
+        decisions:decisions,
+        payout:payout,
+    };
+    Some((result.new_round(), history))
+}
+
+fn run_trainer(num_decks:usize, seed:u64, history_path:Option<String>) {
+    // reshuffle once three quarters of the shoe has been dealt
+    let reshuffle_at = num_decks * 52 / 4;
+
+    // one solver for the session, positions solved in earlier rounds of a shoe stay in its memo
+    let solver = ParallelSolver::new(0).unwrap_or_else(|e| panic!("{}", e));
+    let mut stats = TrainerStats::new();
+    let mut shoe = OptimizedBlackJackGame::new_standard(num_decks);
+    #[cfg(feature = "serde")]
+    let mut history_writer = history_path.map(|x| HandHistoryWriter::append(&x).unwrap_or_else(|e| panic!("{}", e)));
+    #[cfg(not(feature = "serde"))]
+    if history_path.is_some() {
+        panic!("--history needs the serde feature");
+    }
+    let mut source = RngSource::seeded(seed);
+    println!("Blackjack trainer, {} deck shoe, seed {} (replay with --seed {})", num_decks, seed, seed);
+    loop {
+        if shoe.get_deck_count() < reshuffle_at {
+            println!();
+            println!("Reshuffling");
+            shoe = OptimizedBlackJackGame::new_standard(num_decks);
+            // nothing from the old shoe can come up again
+            solver.clear_memo();
+        }
+        match play_round(&shoe, &solver, &mut source, &mut stats) {
+            Some((next, _history)) => {
+                #[cfg(feature = "serde")]
+                if let Some(writer) = history_writer.as_mut() {
+                    writer.write(&_history).unwrap_or_else(|e| panic!("{}", e));
+                }
+                shoe = next;
+            }
+            None => break,
+        }
+    }
+    println!();
+    stats.print_contents();
+}
+
+fn read_count<T: std::str::FromStr>(prompt:&str) -> Option<T> {
+    loop {
+        match read_line(prompt)?.parse::<T>() {
+            Ok(x) => return Some(x),
+            Err(_) => println!("please enter a number"),
+        }
+    }
+}
+
+// one drill per line: time,system,decks,cards,speed_ms,running_answer,running_actual,true_answer,true_actual
+// true count fields are left empty for unbalanced systems
+fn append_drill_record(log_path:&str, record:&str) {
+    let mut file = OpenOptions::new().create(true).append(true).open(log_path).expect("could not open drill log");
+    writeln!(file, "{}", record).expect("could not write drill log");
+}
+
+fn print_drill_history(log_path:&str, system:CountingSystem) {
+    let contents = fs::read_to_string(log_path).unwrap_or_default();
+    let mut drills = 0usize;
+    let mut running_correct = 0usize;
+    let mut true_drills = 0usize;
+    let mut true_correct = 0usize;
+    for line in contents.lines() {
+        let fields = line.split(',').collect::<Vec<&str>>();
+        if fields.len() != 9 || fields[1] != system.get_name() {
+            continue;
+        }
+        drills += 1;
+        if fields[5] == fields[6] {
+            running_correct += 1;
+        }
+        if let (Ok(answer), Ok(actual)) = (fields[7].parse::<f64>(), fields[8].parse::<f64>()) {
+            true_drills += 1;
+            if (answer - actual).abs() <= 0.5 {
+                true_correct += 1;
+            }
+        }
+    }
+    if drills == 0 {
+        return;
+    }
+    println!("All time {} drills: {}, running count accuracy: {:.1}%", system.get_name(), drills, 100.0 * running_correct as f64 / drills as f64);
+    if true_drills > 0 {
+        println!("All time true count accuracy: {:.1}%", 100.0 * true_correct as f64 / true_drills as f64);
+    }
+}
+
+fn run_count_drill(system:CountingSystem, num_decks:usize, cards_per_drill:usize, speed_ms:u64, log_path:&str, seed:u64) {
+    let mut source = RngSource::seeded(seed);
+    let mut deck = new_standard_deck(num_decks);
+    let mut running_count = system.get_initial_running_count(num_decks);
+    println!("Counting drill, {} system, {} deck shoe, {} cards every {}ms, seed {}", system.get_name(), num_decks, cards_per_drill, speed_ms, seed);
+    loop {
+        if deck.count < cards_per_drill {
+            println!("Reshuffling");
+            deck = new_standard_deck(num_decks);
+            running_count = system.get_initial_running_count(num_decks);
+        }
+        match read_line("press enter to start, or [q]uit > ") {
+            Some(x) if x != "q" && x != "quit" => {},
+            _ => break,
+        }
+        for _i in 0..cards_per_drill {
+            let (card, updated_deck) = draw_with(&deck, &mut source).unwrap_or_else(|e| panic!("{}", e));
+            deck = updated_deck;
+            running_count += system.get_tag(card);
+            print!("\r{:>3}", CARD_NAMES[card]);
+            io::stdout().flush().expect("could not flush stdout");
+            thread::sleep(Duration::from_millis(speed_ms));
+            // blank the card briefly so repeated ranks are visible
+            print!("\r   \r");
+            io::stdout().flush().expect("could not flush stdout");
+            thread::sleep(Duration::from_millis(speed_ms / 5));
+        }
+        println!();
+
+        let running_answer = match read_count::<i32>("running count > ") {
+            Some(x) => x,
+            None => break,
+        };
+        if running_answer == running_count {
+            println!("Correct");
+        }
+        else {
+            println!("Wrong, the running count is {}", running_count);
+        }
+
+        let mut true_fields = String::from(",");
+        if system.is_balanced() {
+            let true_count = system.get_true_count(running_count, deck.count);
+            let true_answer = match read_count::<f64>("true count > ") {
+                Some(x) => x,
+                None => break,
+            };
+            if (true_answer - true_count).abs() <= 0.5 {
+                println!("Correct, the true count is {:.2}", true_count);
+            }
+            else {
+                println!("Wrong, the true count is {:.2}", true_count);
+            }
+            true_fields = format!("{},{:.2}", true_answer, true_count);
+        }
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0);
+        append_drill_record(log_path, &format!("{},{},{},{},{},{},{},{}", timestamp, system.get_name(), num_decks, cards_per_drill, speed_ms, running_answer, running_count, true_fields));
+    }
+    println!();
+    print_drill_history(log_path, system);
+}
+
+fn get_option(args:&[String], name:&str) -> Option<String> {
+    let position = args.iter().position(|x| x == name)?;
+    args.get(position + 1).cloned()
+}
+
+fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    let num_decks:Option<usize> = get_option(&args, "--decks").map(|x| x.parse().expect("--decks must be a positive integer"));
+    // a fresh seed each session unless one is given, printed so the session can be replayed
+    let seed:u64 = get_option(&args, "--seed").map(|x| x.parse().expect("--seed must be a non negative integer")).unwrap_or_else(rand::random);
+    match args.get(1).map(|x| x.as_str()) {
+        Some("count") => {
+            let system_name = get_option(&args, "--system").unwrap_or(String::from("hilo"));
+            let system = CountingSystem::from_name(&system_name).unwrap_or_else(|| {
+                let names = CountingSystem::all().iter().map(|x| x.get_name()).collect::<Vec<&str>>().join(", ");
+                panic!("unknown counting system {}, expected one of {}", system_name, names)
+            });
+            let cards_per_drill:usize = get_option(&args, "--cards").map(|x| x.parse().expect("--cards must be a positive integer")).unwrap_or(20);
+            let speed_ms:u64 = get_option(&args, "--speed").map(|x| x.parse().expect("--speed must be in milliseconds")).unwrap_or(1000);
+            let log_path = get_option(&args, "--log").unwrap_or(String::from("count_drills.csv"));
+            run_count_drill(system, num_decks.unwrap_or(6), cards_per_drill, speed_ms, &log_path, seed);
+        }
+        // every decision is solved exactly, which stays interactive on small shoes
+        _ => run_trainer(num_decks.unwrap_or(2), seed, get_option(&args, "--history")),
+    }
+}
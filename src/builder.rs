@@ -0,0 +1,157 @@
+use crate::cache_policy::CachePolicy;
+use crate::error::BlackjackError;
+use crate::opt_bj_game::{get_blackjack_value, new_deck_from_counts, try_remove_cards_from_deck, OptimizedBlackJackGame};
+use crate::ruleset::{Ruleset, Stake};
+
+enum DeckSource {
+    // the shoe before the position's cards came out of it
+    Shoe([usize;10]),
+    StandardShoe(usize),
+    // what is left after the position's cards came out
+    Remaining([usize;10]),
+}
+
+// sets up a game at an arbitrary position. Cards are blackjack value indices, 0 is the ace and 9 the ten values
+#[derive(Default)]
+pub struct OptimizedBlackJackGameBuilder {
+    hand: Vec<usize>,
+    dealer: Vec<usize>,
+    stay: bool,
+    deck: Option<DeckSource>,
+    cache_policy: CachePolicy,
+    ruleset: Ruleset,
+    doubled: bool,
+    stake: Option<Stake>,
+}
+
+fn get_invalid_position(message:&str) -> BlackjackError {
+    BlackjackError::InvalidPosition(String::from(message))
+}
+
+impl OptimizedBlackJackGameBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn hand(mut self, hand:&[usize]) -> Self {
+        self.hand = hand.to_vec();
+        self
+    }
+
+    // the upcard (both face up cards under Double Exposure) first, then any cards the dealer drew
+    // after the player stayed
+    pub fn dealer(mut self, dealer:&[usize]) -> Self {
+        self.dealer = dealer.to_vec();
+        self
+    }
+
+    pub fn stay(mut self, stay:bool) -> Self {
+        self.stay = stay;
+        self
+    }
+
+    // the hand and dealer cards are taken out of this shoe
+    pub fn shoe_counts(mut self, counts:[usize;10]) -> Self {
+        self.deck = Some(DeckSource::Shoe(counts));
+        self
+    }
+
+    pub fn standard_shoe(mut self, num_decks:usize) -> Self {
+        self.deck = Some(DeckSource::StandardShoe(num_decks));
+        self
+    }
+
+    // the deck left after the hand and dealer cards were dealt, used as is
+    pub fn remaining_counts(mut self, counts:[usize;10]) -> Self {
+        self.deck = Some(DeckSource::Remaining(counts));
+        self
+    }
+
+    pub fn cache_policy(mut self, cache_policy:CachePolicy) -> Self {
+        self.cache_policy = cache_policy;
+        self
+    }
+
+    // also picks the shoe for standard_shoe
+    pub fn ruleset(mut self, ruleset:Ruleset) -> Self {
+        self.ruleset = ruleset;
+        self
+    }
+
+    // the last card of the hand is the one it doubled for
+    pub fn doubled(mut self, doubled:bool) -> Self {
+        self.doubled = doubled;
+        self
+    }
+
+    // the whole stake, for positions doubled doesn't describe such as the hands of a split
+    pub fn stake(mut self, stake:Stake) -> Self {
+        self.doubled = stake.doubled;
+        self.stake = Some(stake);
+        self
+    }
+
+    fn check_cards(&self) -> Result<(), BlackjackError> {
+        for card in self.hand.iter().chain(self.dealer.iter()) {
+            if *card >= 10 {
+                return Err(BlackjackError::InvalidCardIndex(*card));
+            }
+        }
+        if self.hand.is_empty() && !self.dealer.is_empty() {
+            return Err(get_invalid_position("the dealer has cards but the hand is empty"));
+        }
+        if !self.hand.is_empty() && self.dealer.is_empty() {
+            return Err(get_invalid_position("the hand is dealt but the dealer has no upcard"));
+        }
+        if self.hand.is_empty() && self.stay {
+            return Err(get_invalid_position("can't stay before the deal"));
+        }
+        // the player can't hit a bust hand
+        for i in 1..self.hand.len() {
+            if get_blackjack_value(&self.hand[..i]) > 21 {
+                return Err(get_invalid_position("the hand kept drawing after it was bust"));
+            }
+        }
+        if self.doubled && (self.hand.len() < 3 || !self.ruleset.can_double(&self.hand[..self.hand.len() - 1])) {
+            return Err(get_invalid_position("the hand couldn't have doubled under these rules"));
+        }
+        if let Some(stake) = self.stake {
+            if stake.split && (self.hand.len() < 2 || !self.ruleset.has_splits()) {
+                return Err(get_invalid_position("the hand couldn't have come from a split under these rules"));
+            }
+        }
+        if self.dealer.len() > self.ruleset.get_dealer_cards_dealt() && !self.stay {
+            return Err(get_invalid_position("the dealer only draws after the player stays"));
+        }
+        // dealer stays on >=17
+        for i in 1..self.dealer.len() {
+            if get_blackjack_value(&self.dealer[..i]) > 16 {
+                return Err(get_invalid_position("the dealer kept drawing after reaching 17"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<OptimizedBlackJackGame, BlackjackError> {
+        self.check_cards()?;
+        let mut dealt = self.hand.clone();
+        dealt.extend(self.dealer.iter());
+        let deck = match self.deck {
+            Some(DeckSource::Shoe(counts)) => try_remove_cards_from_deck(&new_deck_from_counts(&counts), &dealt)?,
+            Some(DeckSource::StandardShoe(num_decks)) => try_remove_cards_from_deck(&self.ruleset.new_deck(num_decks), &dealt)?,
+            Some(DeckSource::Remaining(counts)) => new_deck_from_counts(&counts),
+            None => return Err(get_invalid_position("no deck was given")),
+        };
+        if dealt.is_empty() && deck.count == 0 {
+            return Err(BlackjackError::EmptyDeck);
+        }
+        let game = OptimizedBlackJackGame::from_parts(self.hand, self.dealer, self.stay, deck)
+            .with_cache_policy(self.cache_policy)
+            .with_ruleset(self.ruleset)
+            .with_doubled(self.doubled);
+        Ok(match self.stake {
+            Some(x) => game.with_stake(x),
+            None => game,
+        })
+    }
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+// when OptimizedBlackJackGame writes a solved position to the bin/ file cache. Every policy but
+// Never also reads it, Never leaves bin/ alone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    // positions that took at least this long to solve
+    Elapsed(Duration),
+    // positions whose subtree had at least this many positions in it, cache hits count as one
+    SubtreeSize(usize),
+    // positions with at most this many cards dealt, 0 is the undealt deck
+    MaxDepth(usize),
+    Always,
+    Never,
+}
+
+impl CachePolicy {
+    pub fn should_cache(&self, elapsed:Duration, subtree_size:usize, depth:usize) -> bool {
+        match self {
+            CachePolicy::Elapsed(min_elapsed) => elapsed >= *min_elapsed,
+            CachePolicy::SubtreeSize(min_size) => subtree_size >= *min_size,
+            CachePolicy::MaxDepth(max_depth) => depth <= *max_depth,
+            CachePolicy::Always => true,
+            CachePolicy::Never => false,
+        }
+    }
+
+    pub fn should_read(&self) -> bool {
+        *self != CachePolicy::Never
+    }
+}
+
+// the old hardcoded rule, elapsed().as_secs() > 1, for the binaries that keep bin/ between runs
+pub const FILE_CACHE_POLICY:CachePolicy = CachePolicy::Elapsed(Duration::from_secs(2));
+
+// no file I/O unless the caller asks for it, solving doesn't depend on the current directory
+impl Default for CachePolicy {
+    fn default() -> Self {
+        CachePolicy::Never
+    }
+}
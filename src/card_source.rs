@@ -0,0 +1,93 @@
+use game_pieces_rs::blackjack_deck::BlackjackDeck;
+
+use crate::error::BlackjackError;
+
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use std::collections::VecDeque;
+
+// decides which card comes out next, for the *_with draws. Cards are blackjack value indices
+pub trait CardSource {
+    // counts are what is left in the deck per blackjack value index
+    fn next_card(&mut self, counts:&[usize;10]) -> Result<usize, BlackjackError>;
+}
+
+// draws at random like BlackjackDeck::draw, but from an rng you control
+pub struct RngSource<R: Rng> {
+    rng: R,
+}
+
+impl<R: Rng> RngSource<R> {
+    pub fn new(rng:R) -> Self {
+        Self {
+            rng:rng,
+        }
+    }
+}
+
+impl RngSource<ChaCha8Rng> {
+    // ChaCha8 gives the same stream on every platform, so a seed replays the same cards
+    pub fn seeded(seed:u64) -> Self {
+        Self::new(ChaCha8Rng::seed_from_u64(seed))
+    }
+}
+
+impl<R: Rng> CardSource for RngSource<R> {
+    fn next_card(&mut self, counts:&[usize;10]) -> Result<usize, BlackjackError> {
+        let total:usize = counts.iter().sum();
+        if total == 0 {
+            return Err(BlackjackError::NotEnoughCards { needed:1, available:0 });
+        }
+        let mut pick = self.rng.gen_range(0..total);
+        for (index, count) in counts.iter().enumerate() {
+            if pick < *count {
+                return Ok(index);
+            }
+            pick -= count;
+        }
+        unreachable!("pick is below the total count")
+    }
+}
+
+// a pre-arranged card order, for scenario tests. Errors once it runs out
+pub struct StackedSource {
+    cards: VecDeque<usize>,
+}
+
+impl StackedSource {
+    pub fn new(cards:&[usize]) -> Self {
+        Self {
+            cards:cards.iter().copied().collect::<VecDeque<usize>>(),
+        }
+    }
+
+    pub fn get_remaining(&self) -> usize {
+        self.cards.len()
+    }
+}
+
+impl CardSource for StackedSource {
+    fn next_card(&mut self, _counts:&[usize;10]) -> Result<usize, BlackjackError> {
+        self.cards.pop_front().ok_or(BlackjackError::StackExhausted)
+    }
+}
+
+pub(crate) fn get_next_card<S: CardSource + ?Sized>(counts:&[usize;10], source:&mut S) -> Result<usize, BlackjackError> {
+    let card = source.next_card(counts)?;
+    if card >= 10 {
+        return Err(BlackjackError::InvalidCardIndex(card));
+    }
+    if counts[card] == 0 {
+        return Err(BlackjackError::CardNotAvailable { card:format!("blackjack value index {}", card) });
+    }
+    Ok(card)
+}
+
+pub fn draw_with<S: CardSource + ?Sized>(deck:&BlackjackDeck, source:&mut S) -> Result<(usize, BlackjackDeck), BlackjackError> {
+    let mut counts = [0usize;10];
+    counts.clone_from_slice(&deck.count_by_blackjack_value);
+    let card = get_next_card(&counts, source)?;
+    Ok(deck.draw_blackjack_value_index(card))
+}
@@ -0,0 +1,148 @@
+use crate::action::{Action, ActionExpectedValues};
+use crate::infinite_deck::InfiniteDeckEngine;
+use crate::opt_bj_game::{remove_cards_from_deck, OptimizedBlackJackGame};
+use crate::ruleset::Ruleset;
+
+// dealer upcards in chart column order, 2 through 10 then ace
+pub const UPCARD_ORDER:[usize;10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 0];
+
+pub struct ChartRow {
+    pub label: String,
+    // representative two card hand for the row
+    pub hand: Vec<usize>,
+    // one cell per column, None if the position can't be dealt from the deck
+    pub cells: Vec<Option<ActionExpectedValues>>,
+}
+
+pub struct ChartColumn {
+    pub label: String,
+    // the dealer cards the player sees, the upcard or both face up cards
+    pub dealer: Vec<usize>,
+}
+
+pub struct StrategyChart {
+    pub columns: Vec<ChartColumn>,
+    pub rows: Vec<ChartRow>,
+}
+
+fn get_upcard_label(upcard:usize) -> String {
+    if upcard == 0 { String::from("A") } else { (upcard + 1).to_string() }
+}
+
+// one column per upcard in UPCARD_ORDER. With two dealer cards face up there is one per dealer
+// total the dealer still draws to or stands on, hard 4 to 20 then soft 12 to 20
+pub fn get_chart_columns(ruleset:Ruleset) -> Vec<ChartColumn> {
+    let mut columns = Vec::<ChartColumn>::new();
+    if ruleset.get_dealer_cards_dealt() == 1 {
+        for upcard in UPCARD_ORDER {
+            columns.push(ChartColumn {
+                label:get_upcard_label(upcard),
+                dealer:vec![upcard],
+            });
+        }
+        return columns;
+    }
+    for total in 4usize..=20usize {
+        columns.push(ChartColumn {
+            label:total.to_string(),
+            dealer:if total <= 11 { vec![1, total - 3] } else { vec![9, total - 11] },
+        });
+    }
+    for total in 12usize..=20usize {
+        columns.push(ChartColumn {
+            label:format!("s{}", total),
+            dealer:vec![0, total - 12],
+        });
+    }
+    columns
+}
+
+fn get_chart_hands(ruleset:Ruleset) -> Vec<(String, Vec<usize>)> {
+    let mut hands = Vec::<(String, Vec<usize>)>::new();
+    // hard totals without pairs where possible, 2 + x up to 11 and 10 + x from 12
+    for total in 5usize..=20usize {
+        let hand = if total <= 11 { vec![1, total - 3] } else { vec![9, total - 11] };
+        hands.push((format!("hard {}", total), hand));
+    }
+    for total in 13usize..=20usize {
+        hands.push((format!("soft {}", total), vec![0, total - 12]));
+    }
+    // pairs that can split, in upcard order
+    if ruleset.has_splits() {
+        for card in UPCARD_ORDER.iter().filter(|x| ruleset.can_split(&[**x, **x])) {
+            hands.push((format!("pair {}", get_upcard_label(*card)), vec![*card, *card]));
+        }
+    }
+    hands
+}
+
+impl StrategyChart {
+    pub fn build<F: FnMut(&[usize], usize) -> Option<ActionExpectedValues>>(mut get_action_evs:F) -> Self {
+        Self::build_for(Ruleset::Standard, |hand, dealer| get_action_evs(hand, dealer[0]))
+    }
+
+    // adds pair rows when the rules split, and dealer total columns when both dealer cards are known
+    pub fn build_for<F: FnMut(&[usize], &[usize]) -> Option<ActionExpectedValues>>(ruleset:Ruleset, mut get_action_evs:F) -> Self {
+        let columns = get_chart_columns(ruleset);
+        let mut rows = Vec::<ChartRow>::new();
+        for (label, hand) in get_chart_hands(ruleset) {
+            let cells = columns.iter().map(|x| get_action_evs(&hand, &x.dealer)).collect::<Vec<Option<ActionExpectedValues>>>();
+            rows.push(ChartRow {
+                label:label,
+                hand:hand,
+                cells:cells,
+            });
+        }
+        Self {
+            columns:columns,
+            rows:rows,
+        }
+    }
+
+    pub fn from_infinite_deck(engine:&InfiniteDeckEngine) -> Self {
+        Self::build(|hand, upcard| Some(engine.get_action_expected_values(hand, upcard)))
+    }
+
+    // exact chart for the game's deck, each cell deals the hand and dealer cards out of it first.
+    // Every cell is a full solve, so this is only quick on small decks
+    pub fn from_game(game:&OptimizedBlackJackGame) -> Self {
+        Self::build_for(game.get_ruleset(), |hand, dealer| {
+            let mut cards = hand.to_vec();
+            cards.extend(dealer.iter());
+            let deck = remove_cards_from_deck(game.get_deck(), &cards)?;
+            let position = OptimizedBlackJackGame::from_parts(hand.to_vec(), dealer.to_vec(), false, deck).with_ruleset(game.get_ruleset()).with_cache_policy(game.get_cache_policy());
+            Some(position.get_action_expected_values())
+        })
+    }
+
+    fn print_header(&self) {
+        println!("{:<8} {}", "", self.columns.iter().map(|x| format!("{:>7}", x.label)).collect::<Vec<String>>().join(""));
+    }
+
+    pub fn print_contents(&self) {
+        self.print_header();
+        for row in self.rows.iter() {
+            let cells = row.cells.iter().map(|x| match x {
+                Some(action_evs) => match action_evs.best_action() {
+                    Action::Hit => format!("{:>7}", "H"),
+                    Action::Stay => format!("{:>7}", "S"),
+                    Action::Double => format!("{:>7}", "D"),
+                    Action::Split => format!("{:>7}", "P"),
+                },
+                None => format!("{:>7}", "-"),
+            }).collect::<Vec<String>>();
+            println!("{:<8} {}", row.label, cells.join(""));
+        }
+    }
+
+    pub fn print_expected_values(&self) {
+        self.print_header();
+        for row in self.rows.iter() {
+            let cells = row.cells.iter().map(|x| match x {
+                Some(action_evs) => format!("{:>+7.3}", action_evs.get_best_expected_value()),
+                None => format!("{:>7}", "-"),
+            }).collect::<Vec<String>>();
+            println!("{:<8} {}", row.label, cells.join(""));
+        }
+    }
+}
@@ -0,0 +1,78 @@
+// card counting systems, tags are indexed by blackjack value index (0 = ace, 9 = ten value)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountingSystem {
+    HiLo,
+    Ko,
+    HiOptI,
+    OmegaII,
+    Zen,
+}
+
+impl CountingSystem {
+    pub fn all() -> [CountingSystem;5] {
+        [CountingSystem::HiLo, CountingSystem::Ko, CountingSystem::HiOptI, CountingSystem::OmegaII, CountingSystem::Zen]
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            CountingSystem::HiLo => "hilo",
+            CountingSystem::Ko => "ko",
+            CountingSystem::HiOptI => "hiopt1",
+            CountingSystem::OmegaII => "omega2",
+            CountingSystem::Zen => "zen",
+        }
+    }
+
+    pub fn from_name(name:&str) -> Option<Self> {
+        let name = name.to_lowercase();
+        CountingSystem::all().into_iter().find(|x| x.get_name() == name)
+    }
+
+    pub fn get_tags(&self) -> [i32;10] {
+        match self {
+            CountingSystem::HiLo => [-1, 1, 1, 1, 1, 1, 0, 0, 0, -1],
+            CountingSystem::Ko => [-1, 1, 1, 1, 1, 1, 1, 0, 0, -1],
+            CountingSystem::HiOptI => [0, 0, 1, 1, 1, 1, 0, 0, 0, -1],
+            CountingSystem::OmegaII => [0, 1, 1, 2, 2, 2, 1, 0, -1, -2],
+            CountingSystem::Zen => [-1, 1, 1, 2, 2, 2, 1, 0, 0, -2],
+        }
+    }
+
+    pub fn get_tag(&self, index:usize) -> i32 {
+        self.get_tags()[index]
+    }
+
+    // a full deck sums to zero for balanced systems, KO is the only unbalanced one here
+    pub fn is_balanced(&self) -> bool {
+        *self != CountingSystem::Ko
+    }
+
+    // KO starts below zero so that its pivot lands on a running count of +4
+    pub fn get_initial_running_count(&self, num_decks:usize) -> i32 {
+        if self.is_balanced() {
+            return 0;
+        }
+        4 - 4 * num_decks as i32
+    }
+
+    pub fn get_running_count(&self, cards:&[usize]) -> i32 {
+        cards.iter().map(|x| self.get_tag(*x)).sum()
+    }
+
+    // running count per deck left in the shoe
+    pub fn get_true_count(&self, running_count:i32, cards_remaining:usize) -> f64 {
+        if cards_remaining == 0 {
+            return 0.0;
+        }
+        running_count as f64 / (cards_remaining as f64 / 52.0)
+    }
+
+    // bet ramps key off the true count for balanced systems and the running count for unbalanced ones
+    pub fn get_betting_count(&self, running_count:i32, cards_remaining:usize) -> f64 {
+        if self.is_balanced() {
+            return self.get_true_count(running_count, cards_remaining);
+        }
+        running_count as f64
+    }
+}
@@ -0,0 +1,180 @@
+use game_pieces_rs::blackjack_deck::BlackjackDeck;
+
+use crate::opt_bj_game::get_blackjack_value;
+
+use dashmap::DashMap;
+
+use std::sync::OnceLock;
+
+// dealer outcome indices: 0..=4 for a final 17..=21, then blackjack, bust on 23 or more and
+// bust on exactly 22, which some rulesets push
+pub const DEALER_OUTCOMES:usize = 8;
+pub const DEALER_BLACKJACK:usize = 5;
+pub const DEALER_BUST:usize = 6;
+pub const DEALER_22:usize = 7;
+
+// the outcome for a dealer total over 21
+pub fn get_dealer_bust_outcome(dealer_value:usize) -> usize {
+    if dealer_value == 22 {
+        return DEALER_22;
+    }
+    DEALER_BUST
+}
+
+// the outcome of a finished dealer hand, None while the dealer still has to draw
+pub fn get_dealer_outcome(dealer:&[usize]) -> Option<usize> {
+    let value = get_blackjack_value(dealer);
+    if value == 21 && dealer.len() == 2 {
+        return Some(DEALER_BLACKJACK);
+    }
+    if value > 21 {
+        return Some(get_dealer_bust_outcome(value));
+    }
+    // dealer stays on >=17
+    if value > 16 {
+        return Some(value - 17);
+    }
+    None
+}
+
+// payout for a stayed hand once the dealer has finished, dealer stays on >=17
+pub fn get_stay_payout(hand_value:usize, hand_blackjack:bool, outcome:usize) -> f64 {
+    if outcome == DEALER_BLACKJACK {
+        // you can push though
+        if hand_blackjack {
+            return 0.0;
+        }
+        return -1.0;
+    }
+    if hand_blackjack {
+        return 1.5;
+    }
+    if outcome == DEALER_BUST || outcome == DEALER_22 {
+        return 1.0;
+    }
+    let dealer_value = 17 + outcome;
+    if dealer_value == hand_value {
+        return 0.0;
+    }
+    else if dealer_value > hand_value {
+        return -1.0;
+    }
+    1.0
+}
+
+pub fn get_stay_expected_value(hand_value:usize, hand_blackjack:bool, outcome_probs:&[f64;DEALER_OUTCOMES]) -> f64 {
+    let mut expected_value = 0.0;
+    for (outcome, prob) in outcome_probs.iter().enumerate() {
+        if *prob > 0.0 {
+            expected_value += prob * get_stay_payout(hand_value, hand_blackjack, outcome);
+        }
+    }
+    expected_value
+}
+
+// the dealer's cards sorted, and the deck's count per blackjack value index
+type DealerKey = (Vec<usize>, [usize;10]);
+
+// the dealer draws the same way whatever the player stayed on, so the distribution of dealer
+// outcomes only depends on the dealer's cards and the deck. It is worked out once per
+// (dealer, deck) and shared by every stay decision, across threads. Once the table holds
+// max_len entries it is emptied and starts again, they are cheap to rebuild
+pub struct DealerOutcomeTable {
+    outcomes: DashMap<DealerKey, [f64;DEALER_OUTCOMES]>,
+    max_len: usize,
+}
+
+// around 200 bytes an entry
+pub const DEFAULT_MAX_LEN:usize = 1 << 19;
+
+impl DealerOutcomeTable {
+    pub fn new() -> Self {
+        Self::with_max_len(DEFAULT_MAX_LEN)
+    }
+
+    pub fn with_max_len(max_len:usize) -> Self {
+        Self {
+            outcomes:DashMap::new(),
+            max_len:max_len,
+        }
+    }
+
+    // the table used by OptimizedBlackJackGame
+    pub fn shared() -> &'static DealerOutcomeTable {
+        static SHARED:OnceLock<DealerOutcomeTable> = OnceLock::new();
+        SHARED.get_or_init(DealerOutcomeTable::new)
+    }
+
+    pub fn len(&self) -> usize {
+        self.outcomes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.outcomes.is_empty()
+    }
+
+    pub fn get_max_len(&self) -> usize {
+        self.max_len
+    }
+
+    pub fn clear(&self) {
+        self.outcomes.clear();
+    }
+
+    fn get_key(dealer:&[usize], deck:&BlackjackDeck) -> DealerKey {
+        let mut dealer = dealer.to_vec();
+        dealer.sort();
+        let mut counts = [0usize;10];
+        counts.clone_from_slice(&deck.count_by_blackjack_value);
+        (dealer, counts)
+    }
+
+    pub(crate) fn get(&self, dealer:&[usize], deck:&BlackjackDeck) -> Option<[f64;DEALER_OUTCOMES]> {
+        self.outcomes.get(&Self::get_key(dealer, deck)).map(|x| *x)
+    }
+
+    pub(crate) fn insert(&self, dealer:&[usize], deck:&BlackjackDeck, outcomes:[f64;DEALER_OUTCOMES]) {
+        if self.outcomes.len() >= self.max_len {
+            self.outcomes.clear();
+        }
+        self.outcomes.insert(Self::get_key(dealer, deck), outcomes);
+    }
+
+    // probability of each dealer outcome. If the deck runs out before the dealer reaches 17 that
+    // branch is left out, so the probabilities sum to less than 1, same as the recursion valuing it at 0
+    pub fn get_outcome_probs(&self, dealer:&[usize], deck:&BlackjackDeck) -> [f64;DEALER_OUTCOMES] {
+        let mut outcomes = [0.0f64;DEALER_OUTCOMES];
+        if let Some(outcome) = get_dealer_outcome(dealer) {
+            outcomes[outcome] = 1.0;
+            return outcomes;
+        }
+
+        // nothing is locked while recursing, two threads may both fill in the same entry
+        if let Some(x) = self.get(dealer, deck) {
+            return x;
+        }
+        let draw_probs = deck.draw_probs_by_blackjack_value();
+        let mut dealer_clone:Vec<usize>;
+        let mut drawn_card:usize;
+        let mut drawn_deck:BlackjackDeck;
+        for i in 0usize..10usize {
+            if draw_probs[i] > 0.0 {
+                (drawn_card, drawn_deck) = deck.draw_blackjack_value_index(i);
+                dealer_clone = dealer.to_vec();
+                dealer_clone.push(drawn_card);
+                let drawn_outcomes = self.get_outcome_probs(&dealer_clone, &drawn_deck);
+                for j in 0..DEALER_OUTCOMES {
+                    outcomes[j] += draw_probs[i] * drawn_outcomes[j];
+                }
+            }
+        }
+        self.insert(dealer, deck, outcomes);
+        outcomes
+    }
+}
+
+impl Default for DealerOutcomeTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,75 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum BlackjackError {
+    // tried to draw more cards than are left in the deck
+    NotEnoughCards { needed: usize, available: usize },
+    AlreadyDealt,
+    CacheIo { key: String, source: io::Error },
+    // cache entries are a single big endian f64, anything else is corrupt
+    CorruptCache { key: String, length: usize },
+    SolverThreadPanicked,
+    ThreadPool(String),
+    Cancelled,
+    CheckpointIo { path: String, source: io::Error },
+    // checkpoint lines are a position key and the bits of its f64 ev in hex
+    CorruptCheckpoint { path: String, line: usize },
+    EmptyDeck,
+    // blackjack value indices run 0 (ace) to 9 (ten value)
+    InvalidCardIndex(usize),
+    // removing or placing a card the deck has run out of
+    CardNotAvailable { card: String },
+    // a hand, dealer and deck that can't come from a real deal
+    InvalidPosition(String),
+    // a stacked card source ran out of cards
+    StackExhausted,
+    HistoryIo { path: String, source: io::Error },
+    // line is 1 based, 0 when writing
+    CorruptHistory { path: String, line: usize, message: String },
+    CacheBackend(String),
+    UnknownRuleset(String),
+    // e.g. doubling where the ruleset doesn't allow it
+    ActionNotAllowed(String),
+    // a solver or engine that only plays some rulesets was given another
+    UnsupportedRuleset(String),
+}
+
+impl fmt::Display for BlackjackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlackjackError::NotEnoughCards { needed, available } => write!(f, "not enough cards in the deck, needed {} but only {} left", needed, available),
+            BlackjackError::AlreadyDealt => write!(f, "dealing to an already dealt game"),
+            BlackjackError::CacheIo { key, source } => write!(f, "cache io error for {}: {}", key, source),
+            BlackjackError::CorruptCache { key, length } => write!(f, "corrupt cache entry for {}, expected 8 bytes but it was {}", key, length),
+            BlackjackError::SolverThreadPanicked => write!(f, "a solver thread panicked"),
+            BlackjackError::ThreadPool(message) => write!(f, "could not build solver thread pool: {}", message),
+            BlackjackError::Cancelled => write!(f, "computation was cancelled"),
+            BlackjackError::CheckpointIo { path, source } => write!(f, "checkpoint io error for {}: {}", path, source),
+            BlackjackError::CorruptCheckpoint { path, line } => write!(f, "corrupt checkpoint {} at line {}", path, line),
+            BlackjackError::EmptyDeck => write!(f, "the deck has no cards"),
+            BlackjackError::InvalidCardIndex(index) => write!(f, "{} is not a blackjack value index, expected 0 to 9", index),
+            BlackjackError::CardNotAvailable { card } => write!(f, "{} is not left in the deck", card),
+            BlackjackError::InvalidPosition(message) => write!(f, "invalid position: {}", message),
+            BlackjackError::StackExhausted => write!(f, "the stacked shoe has no cards left"),
+            BlackjackError::HistoryIo { path, source } => write!(f, "hand history io error for {}: {}", path, source),
+            BlackjackError::CorruptHistory { path, line, message } => write!(f, "bad hand history record in {} at line {}: {}", path, line, message),
+            BlackjackError::CacheBackend(message) => write!(f, "ev cache error: {}", message),
+            BlackjackError::UnknownRuleset(name) => write!(f, "unknown ruleset {}", name),
+            BlackjackError::ActionNotAllowed(message) => write!(f, "action not allowed: {}", message),
+            BlackjackError::UnsupportedRuleset(message) => write!(f, "unsupported ruleset: {}", message),
+        }
+    }
+}
+
+impl Error for BlackjackError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BlackjackError::CacheIo { source, .. } => Some(source),
+            BlackjackError::CheckpointIo { source, .. } => Some(source),
+            BlackjackError::HistoryIo { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
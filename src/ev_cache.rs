@@ -0,0 +1,140 @@
+use crate::action::ActionExpectedValues;
+use crate::error::BlackjackError;
+
+use redis::Commands;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// solved positions shared between requests, keyed by rules and OptimizedBlackJackGame::unique_key
+pub trait EvCache: Send + Sync {
+    fn get(&self, key:&str) -> Result<Option<ActionExpectedValues>, BlackjackError>;
+    fn insert(&self, key:&str, action_evs:ActionExpectedValues) -> Result<(), BlackjackError>;
+}
+
+// once it holds capacity entries it is emptied and starts again, unbounded by default
+pub struct MemoryCache {
+    entries: Mutex<HashMap<String, ActionExpectedValues>>,
+    capacity: usize,
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self::with_capacity(usize::MAX)
+    }
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity:usize) -> Self {
+        Self {
+            entries:Mutex::new(HashMap::new()),
+            capacity:capacity,
+        }
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl EvCache for MemoryCache {
+    fn get(&self, key:&str) -> Result<Option<ActionExpectedValues>, BlackjackError> {
+        Ok(self.entries.lock().unwrap_or_else(|e| e.into_inner()).get(key).copied())
+    }
+
+    fn insert(&self, key:&str, action_evs:ActionExpectedValues) -> Result<(), BlackjackError> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= self.capacity && !entries.contains_key(key) {
+            entries.clear();
+        }
+        entries.insert(key.to_string(), action_evs);
+        Ok(())
+    }
+}
+
+// values are "<hit bits> <stay bits>", then "<double bits>" if the hand could double and
+// "<split bits>" if it could split, with "-" for a double it couldn't take. Each f64 is 16 hex
+// digits so they come back exactly. Connections are checked out of a pool, the lock is only
+// held to take one out or put it back, never during a round trip
+pub struct RedisCache {
+    client: redis::Client,
+    connections: Mutex<Vec<redis::Connection>>,
+    prefix: String,
+}
+
+fn get_cache_backend_error(error:redis::RedisError) -> BlackjackError {
+    BlackjackError::CacheBackend(error.to_string())
+}
+
+impl RedisCache {
+    // every key is stored under prefix, so several deployments can share one redis
+    pub fn connect(url:&str, prefix:&str) -> Result<Self, BlackjackError> {
+        let client = redis::Client::open(url).map_err(get_cache_backend_error)?;
+        // fail now on a bad url or an unreachable server, not on the first request
+        let connection = client.get_connection().map_err(get_cache_backend_error)?;
+        Ok(Self {
+            client:client,
+            connections:Mutex::new(vec![connection]),
+            prefix:prefix.to_string(),
+        })
+    }
+
+    // runs f on a pooled connection, opening another when every pooled one is in use. A
+    // connection that errored is dropped rather than put back
+    fn with_connection<T>(&self, f:impl FnOnce(&mut redis::Connection) -> redis::RedisResult<T>) -> Result<T, BlackjackError> {
+        let pooled = self.connections.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        let mut connection = match pooled {
+            Some(x) => x,
+            None => self.client.get_connection().map_err(get_cache_backend_error)?,
+        };
+        let result = f(&mut connection).map_err(get_cache_backend_error)?;
+        self.connections.lock().unwrap_or_else(|e| e.into_inner()).push(connection);
+        Ok(result)
+    }
+}
+
+impl EvCache for RedisCache {
+    fn get(&self, key:&str) -> Result<Option<ActionExpectedValues>, BlackjackError> {
+        let value:Option<String> = self.with_connection(|x| x.get(format!("{}{}", self.prefix, key)))?;
+        let value = match value {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let bits = value.split(' ').map(|x| match x {
+            "-" => Ok(None),
+            _ => u64::from_str_radix(x, 16).map(|x| Some(f64::from_bits(x))),
+        }).collect::<Result<Vec<Option<f64>>, _>>();
+        match bits.as_deref() {
+            Ok([Some(hit), Some(stay), rest @ ..]) if rest.len() <= 2 => Ok(Some(ActionExpectedValues {
+                hit:*hit,
+                stay:*stay,
+                double:rest.first().copied().flatten(),
+                split:rest.get(1).copied().flatten(),
+            })),
+            _ => Err(BlackjackError::CacheBackend(format!("corrupt redis entry for {}: {}", key, value))),
+        }
+    }
+
+    fn insert(&self, key:&str, action_evs:ActionExpectedValues) -> Result<(), BlackjackError> {
+        let mut value = format!("{:016x} {:016x}", action_evs.hit.to_bits(), action_evs.stay.to_bits());
+        match (action_evs.double, action_evs.split) {
+            (Some(double), None) => value += &format!(" {:016x}", double.to_bits()),
+            (Some(double), Some(split)) => value += &format!(" {:016x} {:016x}", double.to_bits(), split.to_bits()),
+            (None, Some(split)) => value += &format!(" - {:016x}", split.to_bits()),
+            (None, None) => {}
+        }
+        self.with_connection(|x| x.set::<_, _, ()>(format!("{}{}", self.prefix, key), value))
+    }
+}
@@ -0,0 +1,231 @@
+// the C API declared in include/blackjack_rs.h, which the capi feature regenerates. Positions are opaque
+// heap objects owned by the caller until bj_position_free. Cards are blackjack value indices,
+// 0 is the ace and 9 the ten values. No panic crosses the boundary
+use crate::action::Action;
+use crate::cache_policy::CachePolicy;
+use crate::error::BlackjackError;
+use crate::opt_bj_game::OptimizedBlackJackGame;
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+pub struct BjPosition {
+    game: OptimizedBlackJackGame,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BjStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidCardIndex = 2,
+    InvalidPosition = 3,
+    EmptyDeck = 4,
+    NotEnoughCards = 5,
+    CardNotAvailable = 6,
+    // anything else the solver returned, bj_last_error_message has the details
+    SolverError = 7,
+    Panic = 8,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BjAction {
+    Hit = 0,
+    Stay = 1,
+    Double = 2,
+    Split = 3,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BjActionEvs {
+    pub hit: f64,
+    pub stay: f64,
+    pub best_action: BjAction,
+    pub best_expected_value: f64,
+    // double_ev is only meaningful when can_double is set
+    pub can_double: bool,
+    pub double_ev: f64,
+    // split_ev is only meaningful when can_split is set
+    pub can_split: bool,
+    pub split_ev: f64,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message:String) {
+    // interior nuls would cut the message short, so they are dropped
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|x| *x.borrow_mut() = Some(message));
+}
+
+fn get_status(error:&BlackjackError) -> BjStatus {
+    match error {
+        BlackjackError::InvalidCardIndex(_) => BjStatus::InvalidCardIndex,
+        BlackjackError::InvalidPosition(_) => BjStatus::InvalidPosition,
+        BlackjackError::EmptyDeck => BjStatus::EmptyDeck,
+        BlackjackError::NotEnoughCards { .. } => BjStatus::NotEnoughCards,
+        BlackjackError::CardNotAvailable { .. } => BjStatus::CardNotAvailable,
+        _ => BjStatus::SolverError,
+    }
+}
+
+// runs f, turning errors and panics into a status and the thread's last error message
+fn call<F: FnOnce() -> Result<(), BlackjackError>>(f:F) -> BjStatus {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => BjStatus::Ok,
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            get_status(&e)
+        },
+        Err(_) => {
+            set_last_error(String::from("the solver panicked"));
+            BjStatus::Panic
+        },
+    }
+}
+
+unsafe fn get_cards<'a>(cards:*const usize, len:usize) -> Option<&'a [usize]> {
+    if len == 0 {
+        return Some(&[]);
+    }
+    if cards.is_null() {
+        return None;
+    }
+    Some(slice::from_raw_parts(cards, len))
+}
+
+// every position handed to C goes through here, with the file cache off so a host program never
+// finds a bin/ directory next to it
+unsafe fn write_position(out:*mut *mut BjPosition, game:OptimizedBlackJackGame) {
+    *out = Box::into_raw(Box::new(BjPosition { game:game.with_cache_policy(CachePolicy::Never) }));
+}
+
+// a message for the last failed call on this thread, or null. Valid until the next failing call
+// on the same thread, don't free it
+#[no_mangle]
+pub extern "C" fn bj_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|x| match x.borrow().as_ref() {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+/// an undealt game from 10 counts, one per blackjack value index
+///
+/// # Safety
+/// counts points to 10 values and out is writable.
+#[no_mangle]
+pub unsafe extern "C" fn bj_position_from_counts(counts:*const usize, out:*mut *mut BjPosition) -> BjStatus {
+    if counts.is_null() || out.is_null() {
+        return BjStatus::NullPointer;
+    }
+    call(|| {
+        let mut deck_counts = [0usize;10];
+        deck_counts.copy_from_slice(slice::from_raw_parts(counts, 10));
+        write_position(out, OptimizedBlackJackGame::from_counts(&deck_counts)?);
+        Ok(())
+    })
+}
+
+/// a dealt position. The hand and dealer cards are removed from the 10 shoe_counts, or with
+/// counts_are_remaining the counts are taken as the deck left after they came out
+///
+/// # Safety
+/// hand and dealer point to hand_len and dealer_len values (they may be null when the length is
+/// 0), shoe_counts points to 10 values and out is writable.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn bj_position_new(hand:*const usize, hand_len:usize, dealer:*const usize, dealer_len:usize, stay:bool, shoe_counts:*const usize, counts_are_remaining:bool, out:*mut *mut BjPosition) -> BjStatus {
+    if shoe_counts.is_null() || out.is_null() {
+        return BjStatus::NullPointer;
+    }
+    let (hand, dealer) = match (get_cards(hand, hand_len), get_cards(dealer, dealer_len)) {
+        (Some(x), Some(y)) => (x, y),
+        _ => return BjStatus::NullPointer,
+    };
+    call(|| {
+        let mut deck_counts = [0usize;10];
+        deck_counts.copy_from_slice(slice::from_raw_parts(shoe_counts, 10));
+        let builder = OptimizedBlackJackGame::builder().hand(hand).dealer(dealer).stay(stay);
+        let builder = if counts_are_remaining { builder.remaining_counts(deck_counts) } else { builder.shoe_counts(deck_counts) };
+        write_position(out, builder.build()?);
+        Ok(())
+    })
+}
+
+/// # Safety
+/// position comes from bj_position_* and hasn't been freed, out is writable.
+#[no_mangle]
+pub unsafe extern "C" fn bj_position_action_evs(position:*const BjPosition, out:*mut BjActionEvs) -> BjStatus {
+    if position.is_null() || out.is_null() {
+        return BjStatus::NullPointer;
+    }
+    call(|| {
+        let action_evs = (*position).game.try_get_action_expected_values()?;
+        *out = BjActionEvs {
+            hit:action_evs.hit,
+            stay:action_evs.stay,
+            best_action:match action_evs.best_action() {
+                Action::Hit => BjAction::Hit,
+                Action::Stay => BjAction::Stay,
+                Action::Double => BjAction::Double,
+                Action::Split => BjAction::Split,
+            },
+            best_expected_value:action_evs.get_best_expected_value(),
+            can_double:action_evs.double.is_some(),
+            double_ev:action_evs.double.unwrap_or(0.0),
+            can_split:action_evs.split.is_some(),
+            split_ev:action_evs.split.unwrap_or(0.0),
+        };
+        Ok(())
+    })
+}
+
+/// the ev of playing the position out with the best strategy, or of the whole round if undealt
+///
+/// # Safety
+/// position comes from bj_position_* and hasn't been freed, out is writable.
+#[no_mangle]
+pub unsafe extern "C" fn bj_position_expected_value(position:*const BjPosition, out:*mut f64) -> BjStatus {
+    if position.is_null() || out.is_null() {
+        return BjStatus::NullPointer;
+    }
+    call(|| {
+        *out = (*position).game.try_get_expected_value()?;
+        Ok(())
+    })
+}
+
+/// writes the 10 counts left in the position's deck
+///
+/// # Safety
+/// position comes from bj_position_* and hasn't been freed, counts has room for 10 values.
+#[no_mangle]
+pub unsafe extern "C" fn bj_position_deck_counts(position:*const BjPosition, counts:*mut usize) -> BjStatus {
+    if position.is_null() || counts.is_null() {
+        return BjStatus::NullPointer;
+    }
+    call(|| {
+        slice::from_raw_parts_mut(counts, 10).copy_from_slice(&(*position).game.get_deck_counts());
+        Ok(())
+    })
+}
+
+/// null is ignored
+///
+/// # Safety
+/// position comes from bj_position_* and is freed once.
+#[no_mangle]
+pub unsafe extern "C" fn bj_position_free(position:*mut BjPosition) {
+    if !position.is_null() {
+        drop(Box::from_raw(position));
+    }
+}
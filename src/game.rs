@@ -1,15 +1,90 @@
-use game_pieces_rs::card::{Suit, Rank, Card};
+use game_pieces_rs::card::Card;
 use game_pieces_rs::deck::Deck;
 
+use crate::card_source::{get_next_card, CardSource};
+use crate::error::BlackjackError;
+use crate::opt_bj_game::{get_blackjack_value, get_cards_for_ranks, get_cards_from_counts, new_deck_from_counts, OptimizedBlackJackGame, STANDARD_RANKS};
+
 use std::fs;
 use std::path::Path;
 
 
+pub fn get_card_indices(cards:&[Card]) -> Vec<usize> {
+    cards.iter().map(|x| x.get_blackjack_value_index()).collect::<Vec<usize>>()
+}
+
+// a card deck only hands out draw probabilities by blackjack value, so the count of each value
+// is kept next to it and updated on every draw
+#[derive(Clone)]
+struct CountedDeck {
+    cards: Deck,
+    count: usize,
+    count_by_blackjack_value: [usize;10],
+}
+
+impl CountedDeck {
+    fn new_empty() -> Self {
+        Self {
+            cards:Deck::new_empty(),
+            count:0,
+            count_by_blackjack_value:[0usize;10],
+        }
+    }
+
+    fn from_vec(cards:Vec<Card>) -> Self {
+        let mut count_by_blackjack_value = [0usize;10];
+        for card in cards.iter() {
+            count_by_blackjack_value[card.get_blackjack_value_index()] += 1;
+        }
+        Self {
+            cards:Deck::from_vec(&cards),
+            count:cards.len(),
+            count_by_blackjack_value:count_by_blackjack_value,
+        }
+    }
+
+    fn get_drawn(&self, card:Card, cards:Deck) -> (Card, Self) {
+        let mut count_by_blackjack_value = self.count_by_blackjack_value;
+        count_by_blackjack_value[card.get_blackjack_value_index()] -= 1;
+        let drawn = Self {
+            cards:cards,
+            count:self.count - 1,
+            count_by_blackjack_value:count_by_blackjack_value,
+        };
+        (card, drawn)
+    }
+
+    fn draw(&self) -> (Card, Self) {
+        let (card, cards) = self.cards.draw();
+        self.get_drawn(card, cards)
+    }
+
+    fn draw_blackjack_value_index(&self, index:usize) -> (Card, Self) {
+        let (card, cards) = self.cards.draw_blackjack_value_index(index);
+        self.get_drawn(card, cards)
+    }
+
+    fn draw_probs_by_blackjack_value(&self) -> [f64;10] {
+        let mut draw_probs = [0.0f64;10];
+        if self.count == 0 {
+            return draw_probs;
+        }
+        for i in 0usize..10usize {
+            draw_probs[i] = self.count_by_blackjack_value[i] as f64 / self.count as f64;
+        }
+        draw_probs
+    }
+}
+
+// serialises as a state::GameState
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "crate::state::GameState", try_from = "crate::state::GameState"))]
 pub struct BlackJackGame {
     hand: Vec<Card>,
     dealer: Vec<Card>,
     stay: bool,
-    deck: Deck,
+    deck: CountedDeck,
 }
 
 impl BlackJackGame {
@@ -18,22 +93,12 @@ impl BlackJackGame {
             hand:Vec::<Card>::new(),
             dealer:Vec::<Card>::new(),
             stay:false,
-            deck:Deck::new_empty(),
+            deck:CountedDeck::new_empty(),
         }
     }
 
     pub fn new_standard(num_decks:usize) -> Self {
-        let mut deck_list = Vec::<Card>::new();
-        let suit_list = vec![Suit::Spades, Suit::Clubs, Suit::Diamonds, Suit::Hearts];
-        let rank_list = vec![Rank::Ace, Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven, Rank::Eight, Rank::Nine, Rank::Ten, Rank::Jack, Rank::Queen, Rank::King];
-        for _i in 0..num_decks {
-            for rank in rank_list.iter(){
-                for suit in suit_list.iter() {
-                    deck_list.push(Card::new(*rank, *suit));
-                }
-            }
-        }
-        let deck = Deck::from_vec(&deck_list);
+        let deck = CountedDeck::from_vec(get_cards_for_ranks(num_decks, &STANDARD_RANKS));
 
         Self {
             hand:Vec::<Card>::new(),
@@ -43,10 +108,46 @@ impl BlackJackGame {
         }
     }
 
-    pub fn deal(&self) -> Self {
-        if self.deck.count < 3 {
-            panic!("dealing from deck without enough cards")
+    // an undealt game on exactly these counts per blackjack value index, ten values cycle through 10, J, Q, K
+    pub fn from_counts(counts:&[usize;10]) -> Result<Self, BlackjackError> {
+        if counts.iter().sum::<usize>() == 0 {
+            return Err(BlackjackError::EmptyDeck);
+        }
+        Ok(Self {
+            hand:Vec::<Card>::new(),
+            dealer:Vec::<Card>::new(),
+            stay:false,
+            deck:CountedDeck::from_vec(get_cards_from_counts(counts)),
+        })
+    }
+
+    // an undealt game on a standard shoe with the cards already seen taken out
+    pub fn from_standard_with_removed(num_decks:usize, removed:&[Card]) -> Result<Self, BlackjackError> {
+        if num_decks == 0 {
+            return Err(BlackjackError::EmptyDeck);
+        }
+        let mut deck_list = get_cards_for_ranks(num_decks, &STANDARD_RANKS);
+        for card in removed.iter() {
+            let position = deck_list.iter().position(|x| x == card).ok_or_else(|| BlackjackError::CardNotAvailable { card:format!("{:?}", card) })?;
+            deck_list.remove(position);
+        }
+        Ok(Self {
+            hand:Vec::<Card>::new(),
+            dealer:Vec::<Card>::new(),
+            stay:false,
+            deck:CountedDeck::from_vec(deck_list),
+        })
+    }
+
+    fn check_deck_count(&self, needed:usize) -> Result<(), BlackjackError> {
+        if self.deck.count < needed {
+            return Err(BlackjackError::NotEnoughCards { needed:needed, available:self.deck.count });
         }
+        Ok(())
+    }
+
+    pub fn try_deal(&self) -> Result<Self, BlackjackError> {
+        self.check_deck_count(3)?;
         let mut hand = Vec::<Card>::new();
         let mut dealer = Vec::<Card>::new();
         let (mut card, mut updated_deck) = self.deck.draw();
@@ -58,44 +159,115 @@ impl BlackJackGame {
         // uncomment for 2 dealer cards revealed
         // (card, updated_deck) = updated_deck.draw();
         // dealer.push(card);
-        Self {
+        Ok(Self {
             hand:hand,
             dealer:dealer,
             stay:false,
             deck:updated_deck,
-        }
+        })
     }
 
-    pub fn hit(&self) -> Self {
-        if self.deck.count < 1 {
-            panic!("hitting from deck without enough cards")
-        }
+    pub fn deal(&self) -> Self {
+        self.try_deal().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_hit(&self) -> Result<Self, BlackjackError> {
+        self.check_deck_count(1)?;
         let mut hand = self.hand.clone();
         let dealer = self.dealer.clone();
         let (card, updated_deck) = self.deck.draw();
         hand.push(card);
-        Self {
+        Ok(Self {
             hand:hand,
             dealer:dealer,
             stay:false,
             deck:updated_deck,
-        }
+        })
     }
 
-    pub fn dealer_hit(&self) -> Self {
-        if self.deck.count < 1 {
-            panic!("dealer hitting from deck without enough cards")
-        }
+    pub fn hit(&self) -> Self {
+        self.try_hit().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_dealer_hit(&self) -> Result<Self, BlackjackError> {
+        self.check_deck_count(1)?;
         let hand = self.hand.clone();
         let mut dealer = self.dealer.clone();
         let (card, updated_deck) = self.deck.draw();
         dealer.push(card);
-        Self {
+        Ok(Self {
             hand:hand,
             dealer:dealer,
             stay:false,
             deck:updated_deck,
-        }
+        })
+    }
+
+    pub fn dealer_hit(&self) -> Self {
+        self.try_dealer_hit().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // the card source picks the blackjack value index, the deck picks which card of that value
+    fn draw_with<S: CardSource>(deck:&CountedDeck, source:&mut S) -> Result<(Card, CountedDeck), BlackjackError> {
+        let card = get_next_card(&deck.count_by_blackjack_value, source)?;
+        Ok(deck.draw_blackjack_value_index(card))
+    }
+
+    // deal, hit and dealer_hit with the cards chosen by source instead of the deck's own rng
+    pub fn try_deal_with<S: CardSource>(&self, source:&mut S) -> Result<Self, BlackjackError> {
+        self.check_deck_count(3)?;
+        let mut hand = Vec::<Card>::new();
+        let mut dealer = Vec::<Card>::new();
+        let (mut card, mut updated_deck) = Self::draw_with(&self.deck, source)?;
+        hand.push(card);
+        (card, updated_deck) = Self::draw_with(&updated_deck, source)?;
+        hand.push(card);
+        (card, updated_deck) = Self::draw_with(&updated_deck, source)?;
+        dealer.push(card);
+        Ok(Self {
+            hand:hand,
+            dealer:dealer,
+            stay:false,
+            deck:updated_deck,
+        })
+    }
+
+    pub fn deal_with<S: CardSource>(&self, source:&mut S) -> Self {
+        self.try_deal_with(source).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_hit_with<S: CardSource>(&self, source:&mut S) -> Result<Self, BlackjackError> {
+        self.check_deck_count(1)?;
+        let mut hand = self.hand.clone();
+        let (card, updated_deck) = Self::draw_with(&self.deck, source)?;
+        hand.push(card);
+        Ok(Self {
+            hand:hand,
+            dealer:self.dealer.clone(),
+            stay:false,
+            deck:updated_deck,
+        })
+    }
+
+    pub fn hit_with<S: CardSource>(&self, source:&mut S) -> Self {
+        self.try_hit_with(source).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_dealer_hit_with<S: CardSource>(&self, source:&mut S) -> Result<Self, BlackjackError> {
+        self.check_deck_count(1)?;
+        let mut dealer = self.dealer.clone();
+        let (card, updated_deck) = Self::draw_with(&self.deck, source)?;
+        dealer.push(card);
+        Ok(Self {
+            hand:self.hand.clone(),
+            dealer:dealer,
+            stay:false,
+            deck:updated_deck,
+        })
+    }
+
+    pub fn dealer_hit_with<S: CardSource>(&self, source:&mut S) -> Self {
+        self.try_dealer_hit_with(source).unwrap_or_else(|e| panic!("{}", e))
     }
 
     pub fn stay(&self) -> Self{
@@ -107,38 +279,36 @@ impl BlackJackGame {
         }
     }
 
+    pub fn get_hand(&self) -> &Vec<Card> {
+        &self.hand
+    }
+
+    pub fn get_dealer(&self) -> &Vec<Card> {
+        &self.dealer
+    }
+
+    pub fn is_stay(&self) -> bool {
+        self.stay
+    }
+
+    pub fn get_deck(&self) -> &Deck {
+        &self.deck.cards
+    }
+
+    pub fn get_deck_counts(&self) -> [usize;10] {
+        self.deck.count_by_blackjack_value
+    }
+
+    pub fn get_deck_count(&self) -> usize {
+        self.deck.count
+    }
+
     pub fn get_hand_value(&self) -> usize {
-        let mut value:usize = 0;
-        let mut aces_count:usize = 0;
-        for card in self.hand.iter(){
-            if card.get_blackjack_value_index() == 0 {
-                aces_count += 1;
-            }
-            value += card.get_blackjack_value_index() + 1; 
-        }
-        for _i in 0..aces_count {
-            if value + 10 <= 21 {
-                value += 10;
-            }
-        }
-        value
+        get_blackjack_value(&get_card_indices(&self.hand))
     }
 
     pub fn get_dealer_value(&self) -> usize {
-        let mut value:usize = 0;
-        let mut aces_count:usize = 0;
-        for card in self.dealer.iter(){
-            if card.get_blackjack_value_index() == 0 {
-                aces_count += 1;
-            }
-            value += card.get_blackjack_value_index() + 1;
-        }
-        for _i in 0..aces_count {
-            if value + 10 <= 21 {
-                value += 10;
-            }
-        }
-        value
+        get_blackjack_value(&get_card_indices(&self.dealer))
     }
 
     pub fn is_hand_bust(&self) -> bool {
@@ -154,15 +324,15 @@ impl BlackJackGame {
     }
 
     pub fn is_dealer_blackjack(&self) -> bool {
-        self.get_dealer_value() == 21 && self.hand.len() == 2
+        self.get_dealer_value() == 21 && self.dealer.len() == 2
     }
 
-    pub fn get_hit_expected_value(&self) -> f64 {
+    fn get_hit_expected_value_unchecked(&self) -> f64 {
         let mut expected_value:f64 = 0.0;
         let draw_probs = self.deck.draw_probs_by_blackjack_value();
         let mut hand_clone:Vec<Card>;                
         let mut drawn_card:Card;
-        let mut drawn_deck:Deck;
+        let mut drawn_deck:CountedDeck;
         let mut drawn_game:Self;
         for i in 0usize..10usize {
             //println!("Hit blackjack index {:?} draw probability = {:?}", i, draw_probs[i] );
@@ -177,28 +347,18 @@ impl BlackJackGame {
                     deck:drawn_deck,
                 };
                 
-                expected_value += draw_probs[i] * drawn_game.get_expected_value();
+                expected_value += draw_probs[i] * drawn_game.get_expected_value_unchecked();
             }
         }
         return expected_value;
     }
 
-    pub fn get_stay_expected_value(&self) -> f64 {
-        let stay_game = Self{
-            hand:self.hand.clone(),
-            dealer:self.dealer.clone(),
-            stay: true,
-            deck: self.deck.clone()
-        };
-        stay_game.get_expected_value()
-    }
-
-    pub fn get_deck_expected_value(&self) -> f64 {
+    fn get_deck_expected_value_unchecked(&self) -> f64 {
         let mut expected_value:f64 = 0.0;
         let draw_probs = self.deck.draw_probs_by_blackjack_value();
         let mut dealer_clone:Vec<Card>;                
         let mut drawn_card:Card;
-        let mut drawn_deck:Deck;
+        let mut drawn_deck:CountedDeck;
         let mut drawn_game:Self;
         for i in 0usize..10usize {
             if draw_probs[i] > 0.0 {
@@ -211,16 +371,16 @@ impl BlackJackGame {
                     stay:false,
                     deck:drawn_deck,
                 };
-                expected_value += draw_probs[i] * drawn_game.get_hit_expected_value();
+                expected_value += draw_probs[i] * drawn_game.get_hit_expected_value_unchecked();
             }
         }
         return expected_value;
     }
 
-    pub fn get_expected_value(&self) -> f64 {
+    fn get_expected_value_unchecked(&self) -> f64 {
         // if has not been dealt, calc for each different possible deal
         if self.hand.len() == 0 && self.dealer.len() == 0 {
-            return self.get_deck_expected_value();
+            return self.get_deck_expected_value_unchecked();
         }
 
         // if dealer has blackjack, you cannot win
@@ -245,23 +405,18 @@ impl BlackJackGame {
 
         // if stay, check for payouts
         if self.stay {
-            if self.is_hand_blackjack() {
-                return 1.5;
-            }
-
-            if self.get_dealer_value() > self.get_hand_value() {
-                return -1.0
-            }
-
             // dealer stays on >=17
             if self.get_dealer_value() > 16 {
+                if self.is_hand_blackjack() && !self.is_dealer_blackjack(){
+                    return 1.5;
+                }
                 if self.get_dealer_value() == self.get_hand_value() {
                     return 0.0;
                 }
                 else if self.get_dealer_value() > self.get_hand_value() {
                     return -1.0;
                 }
-                else if self.get_dealer_value() < self.get_hand_value() {
+                else {
                     return 1.0;
                 }
             }
@@ -270,7 +425,7 @@ impl BlackJackGame {
                 let draw_probs = self.deck.draw_probs_by_blackjack_value();
                 let mut dealer_clone:Vec<Card>;                
                 let mut drawn_card:Card;
-                let mut drawn_deck:Deck;
+                let mut drawn_deck:CountedDeck;
                 let mut drawn_game:Self;
                 for i in 0usize..10usize {
                     if draw_probs[i] > 0.0 {
@@ -283,7 +438,7 @@ impl BlackJackGame {
                             stay:true,
                             deck:drawn_deck,
                         };
-                        expected_value += draw_probs[i] * drawn_game.get_expected_value();
+                        expected_value += draw_probs[i] * drawn_game.get_expected_value_unchecked();
                     }
                 }
                 return expected_value;
@@ -291,12 +446,73 @@ impl BlackJackGame {
         }
         else {
             //if not stay, determine expected value for hit or stay, return > value
-            let stay_ev = self.get_stay_expected_value();
-            let hit_ev =self.get_hit_expected_value();
+            let stay_ev = self.stay().get_expected_value_unchecked();
+            let hit_ev =self.get_hit_expected_value_unchecked();
             let expected_value = f64::max(stay_ev, hit_ev);
             return expected_value;
         }
-        panic!("THIS SHOULD NEVER BE REACHED")
+    }
+
+    // the recursion leaves out any branch where the deck runs out, same as the optimized engine,
+    // so a query is only refused when the position can't draw the first card it needs
+    fn check_can_solve(&self) -> Result<(), BlackjackError> {
+        if self.hand.len() == 0 && self.dealer.len() == 0 {
+            return self.check_deck_count(3);
+        }
+        if self.is_dealer_blackjack() || self.is_hand_bust() || self.is_dealer_bust() {
+            return Ok(());
+        }
+        if self.stay && self.get_dealer_value() > 16 {
+            return Ok(());
+        }
+        self.check_deck_count(1)
+    }
+
+    pub fn try_get_hit_expected_value(&self) -> Result<f64, BlackjackError> {
+        self.check_deck_count(1)?;
+        Ok(self.get_hit_expected_value_unchecked())
+    }
+
+    pub fn get_hit_expected_value(&self) -> f64 {
+        self.try_get_hit_expected_value().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get_stay_expected_value(&self) -> Result<f64, BlackjackError> {
+        let stay_game = self.stay();
+        stay_game.check_can_solve()?;
+        Ok(stay_game.get_expected_value_unchecked())
+    }
+
+    pub fn get_stay_expected_value(&self) -> f64 {
+        self.try_get_stay_expected_value().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get_deck_expected_value(&self) -> Result<f64, BlackjackError> {
+        self.check_deck_count(3)?;
+        Ok(self.get_deck_expected_value_unchecked())
+    }
+
+    pub fn get_deck_expected_value(&self) -> f64 {
+        self.try_get_deck_expected_value().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get_expected_value(&self) -> Result<f64, BlackjackError> {
+        self.check_can_solve()?;
+        Ok(self.get_expected_value_unchecked())
+    }
+
+    pub fn get_expected_value(&self) -> f64 {
+        self.try_get_expected_value().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // same position solved by the optimized engine, with its default CachePolicy::Never so nothing
+    // is read from or written to bin/
+    pub fn try_get_optimized_expected_value(&self) -> Result<f64, BlackjackError> {
+        OptimizedBlackJackGame::from(self).try_get_expected_value()
+    }
+
+    pub fn get_optimized_expected_value(&self) -> f64 {
+        self.try_get_optimized_expected_value().unwrap_or_else(|e| panic!("{}", e))
     }
 
     pub fn print_contents(&self) {
@@ -313,4 +529,33 @@ impl BlackJackGame {
         println!("Position EV: {:?}", self.get_expected_value());
 
     }
-}
\ No newline at end of file
+}
+
+impl From<&BlackJackGame> for OptimizedBlackJackGame {
+    fn from(game:&BlackJackGame) -> Self {
+        OptimizedBlackJackGame::from_parts(get_card_indices(&game.hand), get_card_indices(&game.dealer), game.stay, new_deck_from_counts(&game.get_deck_counts()))
+    }
+}
+
+// suits and face cards are not tracked by the optimized engine, so each card index comes back as a representative card.
+// The card engine plays standard hit or stay only, so other rulesets and doubled or split stakes are refused
+impl TryFrom<&OptimizedBlackJackGame> for BlackJackGame {
+    type Error = BlackjackError;
+
+    fn try_from(game:&OptimizedBlackJackGame) -> Result<Self, BlackjackError> {
+        if !game.get_ruleset().is_standard() || !game.get_stake().is_single() {
+            return Err(BlackjackError::UnsupportedRuleset(format!("the card engine only plays standard rules, not {}", game.get_ruleset())));
+        }
+        let to_cards = |indices:&[usize]| indices.iter().map(|x| {
+            let mut counts = [0usize;10];
+            counts[*x] = 1;
+            get_cards_from_counts(&counts).remove(0)
+        }).collect::<Vec<Card>>();
+        Ok(Self {
+            hand:to_cards(game.get_hand()),
+            dealer:to_cards(game.get_dealer()),
+            stay:game.is_stay(),
+            deck:CountedDeck::from_vec(get_cards_from_counts(&game.get_deck_counts())),
+        })
+    }
+}
@@ -0,0 +1,120 @@
+use crate::action::{Action, ActionExpectedValues};
+use crate::error::BlackjackError;
+use crate::opt_bj_game::OptimizedBlackJackGame;
+use crate::parallel_solver::ParallelSolver;
+
+#[cfg(feature = "serde")]
+use std::fs::{File, OpenOptions};
+#[cfg(feature = "serde")]
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+// one hit or stay decision. The evs are what the solver said at the table
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecisionRecord {
+    // the player's cards when the decision was made
+    pub hand: Vec<usize>,
+    pub action: Action,
+    pub recommended: Action,
+    pub hit_ev: f64,
+    pub stay_ev: f64,
+}
+
+// one round, one JSON object per line in a history file. Cards are blackjack value indices
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HandHistory {
+    pub round: usize,
+    // the deck before the deal
+    pub deck_counts: [usize;10],
+    pub hand: Vec<usize>,
+    // the upcard first, then anything the dealer drew
+    pub dealer: Vec<usize>,
+    pub decisions: Vec<DecisionRecord>,
+    pub payout: f64,
+}
+
+// a stored decision solved again
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayedDecision {
+    pub hand: Vec<usize>,
+    pub action: Action,
+    pub action_evs: ActionExpectedValues,
+    pub ev_lost: f64,
+}
+
+#[cfg(feature = "serde")]
+fn get_history_io_error(path:&Path, source:io::Error) -> BlackjackError {
+    BlackjackError::HistoryIo {
+        path:path.display().to_string(),
+        source:source,
+    }
+}
+
+#[cfg(feature = "serde")]
+pub struct HandHistoryWriter {
+    path: String,
+    writer: BufWriter<File>,
+}
+
+#[cfg(feature = "serde")]
+impl HandHistoryWriter {
+    // appends to the file, creating it if needed
+    pub fn append<P: AsRef<Path>>(path:P) -> Result<Self, BlackjackError> {
+        let path = path.as_ref();
+        let file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| get_history_io_error(path, e))?;
+        Ok(Self {
+            path:path.display().to_string(),
+            writer:BufWriter::new(file),
+        })
+    }
+
+    // each record is flushed as it is written, so a crash loses at most the round in progress
+    pub fn write(&mut self, history:&HandHistory) -> Result<(), BlackjackError> {
+        let line = serde_json::to_string(history).map_err(|e| BlackjackError::CorruptHistory { path:self.path.clone(), line:0, message:e.to_string() })?;
+        writeln!(self.writer, "{}", line).and_then(|_| self.writer.flush()).map_err(|e| get_history_io_error(Path::new(&self.path), e))
+    }
+}
+
+// reads every record, blank lines are skipped
+#[cfg(feature = "serde")]
+pub fn read_hand_histories<P: AsRef<Path>>(path:P) -> Result<Vec<HandHistory>, BlackjackError> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|e| get_history_io_error(path, e))?;
+    let mut histories = Vec::<HandHistory>::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| get_history_io_error(path, e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let history = serde_json::from_str::<HandHistory>(&line).map_err(|e| BlackjackError::CorruptHistory { path:path.display().to_string(), line:i + 1, message:e.to_string() })?;
+        histories.push(history);
+    }
+    Ok(histories)
+}
+
+// solves every stored decision again from the recorded deck, hand and upcard
+pub fn replay_hand_history(history:&HandHistory, solver:&ParallelSolver) -> Result<Vec<ReplayedDecision>, BlackjackError> {
+    let upcard = match history.dealer.first() {
+        Some(x) => *x,
+        None => return Err(BlackjackError::InvalidPosition(String::from("the history has no dealer upcard"))),
+    };
+    let mut replayed = Vec::<ReplayedDecision>::new();
+    for decision in history.decisions.iter() {
+        let position = OptimizedBlackJackGame::builder()
+            .hand(&decision.hand)
+            .dealer(&[upcard])
+            .shoe_counts(history.deck_counts)
+            .build()?;
+        let action_evs = solver.try_get_action_expected_values(&position)?;
+        replayed.push(ReplayedDecision {
+            hand:decision.hand.clone(),
+            action:decision.action,
+            action_evs:action_evs,
+            ev_lost:action_evs.get_expected_value_lost(decision.action),
+        });
+    }
+    Ok(replayed)
+}
@@ -0,0 +1,201 @@
+use crate::action::ActionExpectedValues;
+use crate::dealer_table::{get_dealer_bust_outcome, get_stay_expected_value, DEALER_BLACKJACK, DEALER_OUTCOMES};
+use crate::opt_bj_game::OptimizedBlackJackGame;
+
+use std::collections::HashMap;
+
+// a hand as the dynamic program sees it: hard total (aces as 1), whether it holds an ace,
+// and the card count capped at 3 since only two card hands are special
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct HandState {
+    hard: usize,
+    has_ace: bool,
+    cards: usize,
+}
+
+impl HandState {
+    fn from_cards(cards:&[usize]) -> Self {
+        Self {
+            hard:cards.iter().map(|x| *x + 1).sum(),
+            has_ace:cards.contains(&0),
+            cards:usize::min(cards.len(), 3),
+        }
+    }
+
+    fn draw(&self, index:usize) -> Self {
+        Self {
+            hard:self.hard + index + 1,
+            has_ace:self.has_ace || index == 0,
+            cards:usize::min(self.cards + 1, 3),
+        }
+    }
+
+    fn get_value(&self) -> usize {
+        if self.has_ace && self.hard + 10 <= 21 {
+            return self.hard + 10;
+        }
+        self.hard
+    }
+
+    fn is_blackjack(&self) -> bool {
+        self.get_value() == 21 && self.cards == 2
+    }
+}
+
+// every draw comes from the same fixed probabilities instead of a shrinking deck, so the game
+// collapses to a small dynamic program over (total, soft, dealer upcard). Same rules as the exact
+// engines: no hole card, dealer stays on all 17s, blackjack pays 1.5, hit or stay only
+pub struct InfiniteDeckEngine {
+    draw_probs: [f64;10],
+    dealer_outcomes: [[f64;DEALER_OUTCOMES];10],
+    hit_evs: HashMap<(HandState, usize), f64>,
+}
+
+impl InfiniteDeckEngine {
+    pub fn new(draw_probs:[f64;10]) -> Self {
+        let mut dealer_outcomes = [[0.0f64;DEALER_OUTCOMES];10];
+        for upcard in 0usize..10usize {
+            dealer_outcomes[upcard] = get_dealer_outcome_probs(&draw_probs, HandState::from_cards(&[upcard]));
+        }
+        let mut engine = Self {
+            draw_probs:draw_probs,
+            dealer_outcomes:dealer_outcomes,
+            hit_evs:HashMap::new(),
+        };
+        let mut hit_evs = HashMap::<(HandState, usize), f64>::new();
+        for upcard in 0usize..10usize {
+            for card in 0usize..10usize {
+                engine.get_state_expected_value(HandState::from_cards(&[card]), upcard, &mut hit_evs);
+            }
+        }
+        engine.hit_evs = hit_evs;
+        engine
+    }
+
+    // an infinite shoe of standard decks
+    pub fn new_standard() -> Self {
+        let mut draw_probs = [1.0 / 13.0;10];
+        draw_probs[9] = 4.0 / 13.0;
+        Self::new(draw_probs)
+    }
+
+    // freezes the draw probabilities of the game's current deck
+    pub fn from_game(game:&OptimizedBlackJackGame) -> Self {
+        let deck_probs = game.get_deck().draw_probs_by_blackjack_value();
+        let mut draw_probs = [0.0f64;10];
+        for i in 0usize..10usize {
+            draw_probs[i] = deck_probs[i];
+        }
+        Self::new(draw_probs)
+    }
+
+    pub fn get_draw_probs(&self) -> [f64;10] {
+        self.draw_probs
+    }
+
+    pub fn get_dealer_outcome_probs(&self, upcard:usize) -> [f64;DEALER_OUTCOMES] {
+        self.dealer_outcomes[upcard]
+    }
+
+    fn get_state_stay_expected_value(&self, state:HandState, upcard:usize) -> f64 {
+        if state.get_value() > 21 {
+            return -1.0;
+        }
+        get_stay_expected_value(state.get_value(), state.is_blackjack(), &self.dealer_outcomes[upcard])
+    }
+
+    fn get_state_expected_value(&self, state:HandState, upcard:usize, hit_evs:&mut HashMap<(HandState, usize), f64>) -> f64 {
+        if state.get_value() > 21 {
+            return -1.0;
+        }
+        let stay_ev = self.get_state_stay_expected_value(state, upcard);
+        f64::max(stay_ev, self.get_state_hit_expected_value(state, upcard, hit_evs))
+    }
+
+    // the engine's own table is read first, hit_evs holds whatever it doesn't have yet
+    fn get_state_hit_expected_value(&self, state:HandState, upcard:usize, hit_evs:&mut HashMap<(HandState, usize), f64>) -> f64 {
+        if let Some(x) = self.hit_evs.get(&(state, upcard)).or_else(|| hit_evs.get(&(state, upcard))) {
+            return *x;
+        }
+        let mut expected_value = 0.0;
+        for i in 0usize..10usize {
+            if self.draw_probs[i] > 0.0 {
+                expected_value += self.draw_probs[i] * self.get_state_expected_value(state.draw(i), upcard, hit_evs);
+            }
+        }
+        hit_evs.insert((state, upcard), expected_value);
+        expected_value
+    }
+
+    pub fn get_stay_expected_value(&self, hand:&[usize], upcard:usize) -> f64 {
+        self.get_state_stay_expected_value(HandState::from_cards(hand), upcard)
+    }
+
+    pub fn get_hit_expected_value(&self, hand:&[usize], upcard:usize) -> f64 {
+        let state = HandState::from_cards(hand);
+        if state.get_value() > 21 {
+            return -1.0;
+        }
+        if let Some(x) = self.hit_evs.get(&(state, upcard)) {
+            return *x;
+        }
+        // new fills in every state reachable from a first card, so this is only for odd hands,
+        // and only the states missing from the engine's table are worked out
+        self.get_state_hit_expected_value(state, upcard, &mut HashMap::new())
+    }
+
+    pub fn get_action_expected_values(&self, hand:&[usize], upcard:usize) -> ActionExpectedValues {
+        ActionExpectedValues {
+            hit:self.get_hit_expected_value(hand, upcard),
+            stay:self.get_stay_expected_value(hand, upcard),
+            double:None,
+            split:None,
+        }
+    }
+
+    pub fn get_expected_value(&self, hand:&[usize], upcard:usize) -> f64 {
+        if HandState::from_cards(hand).get_value() > 21 {
+            return -1.0;
+        }
+        self.get_action_expected_values(hand, upcard).get_best_expected_value()
+    }
+
+    // same deal order as OptimizedBlackJackGame::get_deck_expected_value, dealer card then one player card
+    pub fn get_deck_expected_value(&self) -> f64 {
+        let mut expected_value = 0.0;
+        for upcard in 0usize..10usize {
+            for card in 0usize..10usize {
+                expected_value += self.draw_probs[upcard] * self.draw_probs[card] * self.get_expected_value(&[card], upcard);
+            }
+        }
+        expected_value
+    }
+}
+
+fn get_dealer_outcome_probs(draw_probs:&[f64;10], dealer:HandState) -> [f64;DEALER_OUTCOMES] {
+    let mut outcomes = [0.0f64;DEALER_OUTCOMES];
+    let value = dealer.get_value();
+    if value > 21 {
+        outcomes[get_dealer_bust_outcome(value)] = 1.0;
+        return outcomes;
+    }
+    // dealer stays on >=17
+    if value > 16 {
+        if dealer.is_blackjack() {
+            outcomes[DEALER_BLACKJACK] = 1.0;
+        }
+        else {
+            outcomes[value - 17] = 1.0;
+        }
+        return outcomes;
+    }
+    for i in 0usize..10usize {
+        if draw_probs[i] > 0.0 {
+            let drawn = get_dealer_outcome_probs(draw_probs, dealer.draw(i));
+            for j in 0..DEALER_OUTCOMES {
+                outcomes[j] += draw_probs[i] * drawn[j];
+            }
+        }
+    }
+    outcomes
+}
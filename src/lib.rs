@@ -1,2 +1,25 @@
+pub mod action;
+pub mod builder;
+pub mod cache_policy;
+pub mod card_source;
+pub mod chart;
+pub mod counting;
+pub mod dealer_table;
+pub mod error;
+pub mod ev_cache;
+pub mod ffi;
 pub mod game;
-pub mod opt_bj_game;
\ No newline at end of file
+pub mod history;
+pub mod infinite_deck;
+pub mod opt_bj_game;
+pub mod parallel_solver;
+pub mod penetration;
+pub mod progress;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod ruleset;
+pub mod shoe;
+pub mod state;
+pub mod switch;
+pub mod table;
+pub mod validation;
\ No newline at end of file
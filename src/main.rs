@@ -1,22 +1,98 @@
+use blackjack_rs::cache_policy::FILE_CACHE_POLICY;
 use blackjack_rs::game::BlackJackGame;
 use blackjack_rs::opt_bj_game::OptimizedBlackJackGame;
+use blackjack_rs::parallel_solver::ParallelSolver;
+use blackjack_rs::progress::{CancellationToken, SolverProgress};
 
+use std::env;
 use std::fs;
-use std::time::Instant;
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
 
+const PROGRESS_BAR_WIDTH:usize = 40;
+
+fn get_option(args:&[String], name:&str) -> Option<String> {
+    let position = args.iter().position(|x| x == name)?;
+    args.get(position + 1).cloned()
+}
+
+fn print_progress_bar(progress:&SolverProgress) {
+    let filled = (progress.get_fraction_complete() * PROGRESS_BAR_WIDTH as f64) as usize;
+    let eta = match progress.get_estimated_remaining() {
+        Some(x) => format!("{}s", x.as_secs()),
+        None => String::from("?"),
+    };
+    eprint!("\r[{}{}] {:>5.1}% {} nodes, {:.1}% cache hits, eta {}   ",
+        "#".repeat(filled),
+        ".".repeat(PROGRESS_BAR_WIDTH - filled),
+        100.0 * progress.get_fraction_complete(),
+        progress.nodes_explored,
+        100.0 * progress.get_cache_hit_rate(),
+        eta);
+    io::stderr().flush().ok();
+}
+
+const CHECKPOINT_INTERVAL_SECS:u64 = 60;
+
+// solves on the rayon pool by default, --threads caps the pool (0 uses every core) and
+// --timeout cancels after that many seconds. --checkpoint path saves finished positions every
+// minute, add --resume to start from them. --file-cache uses the old recursion with the bin/ cache,
+// which also draws the progress bar and stops at --timeout
 fn main() {
-    // clean up old cache values
-    fs::create_dir_all("bin/").expect("");
-    fs::remove_dir_all("bin/").expect("");
-    fs::create_dir_all("bin/").expect("");
+    let args = env::args().collect::<Vec<String>>();
+    let num_decks:usize = get_option(&args, "--decks").map(|x| x.parse().expect("--decks must be a positive integer")).unwrap_or(11);
 
-    let mut bj_game = OptimizedBlackJackGame::new_standard(11);
+    let bj_game = OptimizedBlackJackGame::new_standard(num_decks);
     //bj_game.print_contents();
     //bj_game = bj_game.deal();
     //bj_game = bj_game.hit();
     //bj_game = bj_game.stay();
     let now = Instant::now();
-    bj_game.print_contents();
+    let cancellation = CancellationToken::new();
+    if let Some(x) = get_option(&args, "--timeout") {
+        let timeout:u64 = x.parse().expect("--timeout must be a number of seconds");
+        let cancellation = cancellation.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_secs(timeout));
+            cancellation.cancel();
+        });
+    }
+    if args.iter().any(|x| x == "--file-cache") {
+        // clean up old cache values
+        fs::create_dir_all("bin/").expect("");
+        fs::remove_dir_all("bin/").expect("");
+        fs::create_dir_all("bin/").expect("");
+        let bj_game = bj_game.with_cache_policy(FILE_CACHE_POLICY).with_cancellation_token(cancellation);
+        let result = bj_game.try_get_expected_value_with_progress(Duration::from_millis(500), print_progress_bar);
+        eprintln!();
+        match result {
+            Ok(x) => println!("Position EV: {:?}", x),
+            Err(e) => println!("{}", e),
+        }
+    }
+    else {
+        let num_threads:usize = get_option(&args, "--threads").map(|x| x.parse().expect("--threads must be a non negative integer")).unwrap_or(0);
+        let mut solver = ParallelSolver::new(num_threads).unwrap_or_else(|e| panic!("{}", e))
+            .with_cancellation_token(cancellation)
+            .with_progress_callback(Duration::from_millis(500), print_progress_bar);
+        if let Some(path) = get_option(&args, "--checkpoint") {
+            solver = solver.with_checkpoint(&path, Duration::from_secs(CHECKPOINT_INTERVAL_SECS));
+            if args.iter().any(|x| x == "--resume") {
+                let loaded = solver.resume_from_checkpoint(&path).unwrap_or_else(|e| panic!("{}", e));
+                println!("resumed {} positions from {}", loaded, path);
+            }
+        }
+        println!("solving {} decks on {} threads", num_decks, solver.get_num_threads());
+        let result = solver.try_get_expected_value(&bj_game);
+        eprintln!();
+        match result {
+            // the parallel solver plays hit or stay only
+            Ok(x) => println!("Position EV: {:?} (hit or stay only, doubling and splitting are not computed)", x),
+            Err(e) => println!("{}", e),
+        }
+        println!("{} positions memoised", solver.get_memo_len());
+    }
     println!("computation took {} seconds", now.elapsed().as_secs_f64())
     
 
@@ -1,17 +1,129 @@
 use game_pieces_rs::blackjack_deck::BlackjackDeck;
 use game_pieces_rs::card::{Suit, Rank, Card};
 
-use std::time::{Instant};
+use crate::action::ActionExpectedValues;
+use crate::builder::OptimizedBlackJackGameBuilder;
+use crate::cache_policy::CachePolicy;
+use crate::card_source::{draw_with, CardSource};
+use crate::dealer_table::{get_dealer_bust_outcome, DealerOutcomeTable, DEALER_BLACKJACK};
+use crate::error::BlackjackError;
+use crate::progress::{CancellationToken, ProgressCounters, QueryTracker, SolverProgress};
+use crate::ruleset::{Ruleset, Stake};
+
+use std::time::{Duration, Instant};
 use std::fs;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
 
 
+pub const STANDARD_RANKS:[Rank;13] = [Rank::Ace, Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven, Rank::Eight, Rank::Nine, Rank::Ten, Rank::Jack, Rank::Queen, Rank::King];
+
+// a standard shoe without the ten spot cards, for Spanish 21. Jacks, queens and kings stay
+pub const SPANISH_21_RANKS:[Rank;12] = [Rank::Ace, Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven, Rank::Eight, Rank::Nine, Rank::Jack, Rank::Queen, Rank::King];
+
+// every suit of each rank, num_decks times
+pub fn get_cards_for_ranks(num_decks:usize, ranks:&[Rank]) -> Vec<Card> {
+    let suit_list = vec![Suit::Spades, Suit::Clubs, Suit::Diamonds, Suit::Hearts];
+    let mut deck_list = Vec::<Card>::new();
+    for _i in 0..num_decks {
+        for rank in ranks.iter(){
+            for suit in suit_list.iter() {
+                deck_list.push(Card::new(*rank, *suit));
+            }
+        }
+    }
+    deck_list
+}
+
+pub fn new_standard_deck(num_decks:usize) -> BlackjackDeck {
+    BlackjackDeck::from_vec(&get_cards_for_ranks(num_decks, &STANDARD_RANKS))
+}
+
+pub fn new_spanish_21_deck(num_decks:usize) -> BlackjackDeck {
+    BlackjackDeck::from_vec(&get_cards_for_ranks(num_decks, &SPANISH_21_RANKS))
+}
+
+// one card per count, cycling through the ranks and suits that share a blackjack value index
+pub fn get_cards_from_counts(counts:&[usize;10]) -> Vec<Card> {
+    let suit_list = vec![Suit::Spades, Suit::Clubs, Suit::Diamonds, Suit::Hearts];
+    let rank_list = vec![Rank::Ace, Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven, Rank::Eight, Rank::Nine];
+    let ten_rank_list = vec![Rank::Ten, Rank::Jack, Rank::Queen, Rank::King];
+    let mut deck_list = Vec::<Card>::new();
+    for (index, count) in counts.iter().enumerate() {
+        let ranks = if index == 9 { ten_rank_list.clone() } else { vec![rank_list[index]] };
+        for i in 0..*count {
+            let rank = ranks[i % ranks.len()];
+            let suit = suit_list[(i / ranks.len()) % suit_list.len()];
+            deck_list.push(Card::new(rank, suit));
+        }
+    }
+    deck_list
+}
+
+pub fn new_deck_from_counts(counts:&[usize;10]) -> BlackjackDeck {
+    BlackjackDeck::from_vec(&get_cards_from_counts(counts))
+}
+
+pub fn try_remove_cards_from_deck(deck:&BlackjackDeck, cards:&[usize]) -> Result<BlackjackDeck, BlackjackError> {
+    let mut updated_deck = deck.clone();
+    for card in cards.iter() {
+        if *card >= 10 {
+            return Err(BlackjackError::InvalidCardIndex(*card));
+        }
+        if updated_deck.count_by_blackjack_value[*card] == 0 {
+            return Err(BlackjackError::CardNotAvailable { card:format!("blackjack value index {}", card) });
+        }
+        (_, updated_deck) = updated_deck.draw_blackjack_value_index(*card);
+    }
+    Ok(updated_deck)
+}
+
+// None if any of the cards is not left in the deck
+pub fn remove_cards_from_deck(deck:&BlackjackDeck, cards:&[usize]) -> Option<BlackjackDeck> {
+    try_remove_cards_from_deck(deck, cards).ok()
+}
+
+pub fn get_blackjack_value(cards:&[usize]) -> usize {
+    let mut value:usize = 0;
+    let mut aces_count:usize = 0;
+    for card in cards.iter(){
+        if *card == 0 {
+            aces_count += 1;
+        }
+        value += *card + 1;
+    }
+    for _i in 0..aces_count {
+        if value + 10 <= 21 {
+            value += 10;
+        }
+    }
+    value
+}
+
+// soft means an ace is currently being counted as 11
+pub fn is_soft(cards:&[usize]) -> bool {
+    let hard_value:usize = cards.iter().map(|x| *x + 1).sum();
+    cards.contains(&0) && hard_value + 10 <= 21
+}
+
+// serialises as a state::GameState
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "crate::state::GameState", try_from = "crate::state::GameState"))]
 pub struct OptimizedBlackJackGame {
     hand: Vec<usize>,
     dealer: Vec<usize>,
     stay: bool,
     deck: BlackjackDeck,
+    // carried over to every position reached from this one
+    cache_policy: CachePolicy,
+    ruleset: Ruleset,
+    // the bet on the hand. Once it has doubled and taken its one card it can only stay (or rescue)
+    stake: Stake,
+    // carried over like cache_policy, None unless a query asked for cancellation or progress
+    tracker: Option<QueryTracker>,
 }
 
 impl OptimizedBlackJackGame {
@@ -21,30 +133,130 @@ impl OptimizedBlackJackGame {
             dealer:Vec::<usize>::new(),
             stay:false,
             deck:BlackjackDeck::new_empty(),
+            cache_policy:CachePolicy::default(),
+            tracker:None,
+            ruleset:Ruleset::default(),
+            stake:Stake::SINGLE,
         }
     }
 
     pub fn new_standard(num_decks:usize) -> Self {
-        let mut deck_list = Vec::<Card>::new();
-        let suit_list = vec![Suit::Spades, Suit::Clubs, Suit::Diamonds, Suit::Hearts];
-        let rank_list = vec![Rank::Ace, Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven, Rank::Eight, Rank::Nine, Rank::Ten, Rank::Jack, Rank::Queen, Rank::King];
-        for _i in 0..num_decks {
-            for rank in rank_list.iter(){
-                for suit in suit_list.iter() {
-                    deck_list.push(Card::new(*rank, *suit));
-                }
-            }
+        Self {
+            hand:Vec::<usize>::new(),
+            dealer:Vec::<usize>::new(),
+            stay:false,
+            deck:new_standard_deck(num_decks),
+            cache_policy:CachePolicy::default(),
+            tracker:None,
+            ruleset:Ruleset::default(),
+            stake:Stake::SINGLE,
         }
-        let deck = BlackjackDeck::from_vec(&deck_list);
+    }
 
+    // an undealt game on an arbitrary deck
+    pub fn new_from_deck(deck:BlackjackDeck) -> Self {
         Self {
             hand:Vec::<usize>::new(),
             dealer:Vec::<usize>::new(),
             stay:false,
             deck:deck,
+            cache_policy:CachePolicy::default(),
+            tracker:None,
+            ruleset:Ruleset::default(),
+            stake:Stake::SINGLE,
+        }
+    }
+
+    pub fn builder() -> OptimizedBlackJackGameBuilder {
+        OptimizedBlackJackGameBuilder::new()
+    }
+
+    // an undealt game on exactly these counts per blackjack value index
+    pub fn from_counts(counts:&[usize;10]) -> Result<Self, BlackjackError> {
+        if counts.iter().sum::<usize>() == 0 {
+            return Err(BlackjackError::EmptyDeck);
+        }
+        Ok(Self::new_from_deck(new_deck_from_counts(counts)))
+    }
+
+    // an undealt game on a standard shoe with the cards already seen taken out, by blackjack value index
+    pub fn from_standard_with_removed(num_decks:usize, removed:&[usize]) -> Result<Self, BlackjackError> {
+        if num_decks == 0 {
+            return Err(BlackjackError::EmptyDeck);
+        }
+        Ok(Self::new_from_deck(try_remove_cards_from_deck(&new_standard_deck(num_decks), removed)?))
+    }
+
+    pub(crate) fn from_parts(hand:Vec<usize>, dealer:Vec<usize>, stay:bool, deck:BlackjackDeck) -> Self {
+        Self {
+            hand:hand,
+            dealer:dealer,
+            stay:stay,
+            deck:deck,
+            cache_policy:CachePolicy::default(),
+            tracker:None,
+            ruleset:Ruleset::default(),
+            stake:Stake::SINGLE,
         }
     }
 
+    pub fn with_cache_policy(mut self, cache_policy:CachePolicy) -> Self {
+        self.cache_policy = cache_policy;
+        self
+    }
+
+    pub fn get_cache_policy(&self) -> CachePolicy {
+        self.cache_policy
+    }
+
+    // cancel the token from any thread and the try_ queries on this position, or on any position
+    // reached from it, return BlackjackError::Cancelled
+    pub fn with_cancellation_token(mut self, cancellation:CancellationToken) -> Self {
+        let mut tracker = self.tracker.unwrap_or_default();
+        tracker.cancellation = cancellation;
+        self.tracker = Some(tracker);
+        self
+    }
+
+    // an undealt game on the ruleset's own shoe
+    pub fn new_with_ruleset(ruleset:Ruleset, num_decks:usize) -> Self {
+        Self::new_from_deck(ruleset.new_deck(num_decks)).with_ruleset(ruleset)
+    }
+
+    // the rules this position and every one reached from it are solved under, the deck is left as is
+    pub fn with_ruleset(mut self, ruleset:Ruleset) -> Self {
+        self.ruleset = ruleset;
+        self
+    }
+
+    pub fn get_ruleset(&self) -> Ruleset {
+        self.ruleset
+    }
+
+    // the last card of the hand is the one it doubled for, the stake comes from the ruleset
+    pub(crate) fn with_doubled(mut self, doubled:bool) -> Self {
+        self.stake = if doubled && self.hand.len() > 1 {
+            self.ruleset.get_double_stake(&self.hand[..self.hand.len() - 1], Stake::SINGLE)
+        }
+        else {
+            Stake::SINGLE
+        };
+        self
+    }
+
+    pub(crate) fn with_stake(mut self, stake:Stake) -> Self {
+        self.stake = stake;
+        self
+    }
+
+    pub fn get_stake(&self) -> Stake {
+        self.stake
+    }
+
+    pub fn is_doubled(&self) -> bool {
+        self.stake.doubled
+    }
+
     pub fn unique_key(&self) -> String {
         let count = self.deck.count;
         let mut count_by_blackjack_value = [0usize;10];
@@ -57,32 +269,58 @@ impl OptimizedBlackJackGame {
         dealer.sort();
         let dealer_index_string = dealer.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("-");
         let stay_string =self.stay.to_string();
-        format!("[c={count}cbji=[{bjvc_string}]h=[{hand_index_string}]d=[{dealer_index_string}]s={stay_string}]")
+        // v2 since stays against a dealer under 17 were fixed, so bin/ files from before are never read
+        if self.ruleset.is_standard() && self.stake.is_single() {
+            return format!("v2[c={count}cbji=[{bjvc_string}]h=[{hand_index_string}]d=[{dealer_index_string}]s={stay_string}]");
+        }
+        let ruleset_string = self.ruleset.to_string();
+        let doubled_string = self.stake.doubled.to_string();
+        // free and split stakes only come up under rules that have them
+        if self.stake.free == 0 && !self.stake.split {
+            return format!("v2[c={count}cbji=[{bjvc_string}]h=[{hand_index_string}]d=[{dealer_index_string}]s={stay_string}r={ruleset_string}x={doubled_string}]");
+        }
+        let at_risk = self.stake.at_risk;
+        let free = self.stake.free;
+        let split_string = self.stake.split.to_string();
+        format!("v2[c={count}cbji=[{bjvc_string}]h=[{hand_index_string}]d=[{dealer_index_string}]s={stay_string}r={ruleset_string}x={doubled_string}a={at_risk}f={free}p={split_string}]")
     }
     
-    pub fn write_bin_file_cache(&self, key:String, ev:f64) {
+    pub fn try_write_bin_file_cache(&self, key:String, ev:f64) -> Result<(), BlackjackError> {
         let filepath = format!("bin/{key}.data");
-        fs::write(filepath, ev.to_be_bytes()).expect("cannot write to file");
+        fs::write(filepath, ev.to_be_bytes()).map_err(|e| BlackjackError::CacheIo { key:key, source:e })
     }
 
-    pub fn read_bin_file_cache(&self, key:String) -> Option<f64>{
+    pub fn write_bin_file_cache(&self, key:String, ev:f64) {
+        self.try_write_bin_file_cache(key, ev).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_read_bin_file_cache(&self, key:String) -> Result<Option<f64>, BlackjackError> {
         let filepath = format!("bin/{key}.data");
         if Path::new(&filepath).exists() {
-            let bytes = fs::read(&filepath).expect("could not read file");
-            let bytes_array = bytes.clone().try_into().unwrap_or_else(|v: Vec<u8>| panic!("Expected a Vec of length {} but it was {}", 8, v.len()));
+            let bytes = fs::read(&filepath).map_err(|e| BlackjackError::CacheIo { key:key.clone(), source:e })?;
+            let bytes_array:[u8;8] = bytes.try_into().map_err(|v: Vec<u8>| BlackjackError::CorruptCache { key:key, length:v.len() })?;
             let ev:f64 = f64::from_be_bytes(bytes_array);
-            return Some(ev);
+            return Ok(Some(ev));
         }
-        return None;
+        return Ok(None);
     }
 
-    pub fn deal(&self) -> Self {
-        if self.deck.count < 3 {
-            panic!("dealing from deck without enough cards")
+    pub fn read_bin_file_cache(&self, key:String) -> Option<f64>{
+        self.try_read_bin_file_cache(key).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn check_deck_count(&self, needed:usize) -> Result<(), BlackjackError> {
+        if self.deck.count < needed {
+            return Err(BlackjackError::NotEnoughCards { needed:needed, available:self.deck.count });
         }
+        Ok(())
+    }
+
+    pub fn try_deal(&self) -> Result<Self, BlackjackError> {
+        self.check_deck_count(2 + self.ruleset.get_dealer_cards_dealt())?;
 
         if self.hand.len() > 0 || self.dealer.len() > 0 {
-            panic!("dealing to an already dealt game")
+            return Err(BlackjackError::AlreadyDealt);
         }
         let mut hand = Vec::<usize>::new();
         let mut dealer = Vec::<usize>::new();
@@ -92,47 +330,234 @@ impl OptimizedBlackJackGame {
         hand.push(card);
         (card, updated_deck) = updated_deck.draw();
         dealer.push(card);
-        // uncomment for 2 dealer cards revealed
-        // (card, updated_deck) = updated_deck.draw();
-        // dealer.push(card);
-        Self {
+        // Double Exposure deals the second dealer card face up
+        while dealer.len() < self.ruleset.get_dealer_cards_dealt() {
+            (card, updated_deck) = updated_deck.draw();
+            dealer.push(card);
+        }
+        Ok(Self {
             hand:hand,
             dealer:dealer,
             stay:false,
             deck:updated_deck,
+            cache_policy:self.cache_policy,
+            tracker:self.tracker.clone(),
+            ruleset:self.ruleset,
+            stake:Stake::SINGLE,
+        })
+    }
+
+    pub fn deal(&self) -> Self {
+        self.try_deal().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn check_can_hit(&self) -> Result<(), BlackjackError> {
+        if self.stake.doubled {
+            return Err(BlackjackError::ActionNotAllowed(String::from("a doubled hand only gets one card")));
         }
+        Ok(())
     }
 
-    pub fn hit(&self) -> Self {
-        if self.deck.count < 1 {
-            panic!("hitting from deck without enough cards")
+    fn check_can_double(&self) -> Result<(), BlackjackError> {
+        if self.stake.doubled || self.stay || self.dealer.is_empty() || !self.ruleset.can_double(&self.hand) {
+            return Err(BlackjackError::ActionNotAllowed(format!("can't double {:?} under {} rules", self.hand, self.ruleset)));
+        }
+        Ok(())
+    }
+
+    fn check_can_split(&self) -> Result<(), BlackjackError> {
+        if self.stake.doubled || self.stake.split || self.stay || self.dealer.is_empty() || !self.ruleset.can_split(&self.hand) {
+            return Err(BlackjackError::ActionNotAllowed(format!("can't split {:?} under {} rules", self.hand, self.ruleset)));
         }
+        Ok(())
+    }
+
+    pub fn try_hit(&self) -> Result<Self, BlackjackError> {
+        self.check_can_hit()?;
+        self.check_deck_count(1)?;
         let mut hand = self.hand.clone();
         let dealer = self.dealer.clone();
         let (card, updated_deck) = self.deck.draw();
         hand.push(card);
-        Self {
+        Ok(Self {
             hand:hand,
             dealer:dealer,
             stay:false,
             deck:updated_deck,
-        }
+            cache_policy:self.cache_policy,
+            tracker:self.tracker.clone(),
+            ruleset:self.ruleset,
+            stake:self.stake,
+        })
     }
 
-    pub fn dealer_hit(&self) -> Self {
-        if self.deck.count < 1 {
-            panic!("dealer hitting from deck without enough cards")
-        }
+    pub fn hit(&self) -> Self {
+        self.try_hit().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_dealer_hit(&self) -> Result<Self, BlackjackError> {
+        self.check_deck_count(1)?;
         let hand = self.hand.clone();
         let mut dealer = self.dealer.clone();
         let (card, updated_deck) = self.deck.draw();
         dealer.push(card);
-        Self {
+        Ok(Self {
             hand:hand,
             dealer:dealer,
             stay:false,
             deck:updated_deck,
+            cache_policy:self.cache_policy,
+            tracker:self.tracker.clone(),
+            ruleset:self.ruleset,
+            stake:self.stake,
+        })
+    }
+
+    pub fn dealer_hit(&self) -> Self {
+        self.try_dealer_hit().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // deal, hit and dealer_hit with the cards chosen by source instead of the deck's own rng
+    pub fn try_deal_with<S: CardSource>(&self, source:&mut S) -> Result<Self, BlackjackError> {
+        self.check_deck_count(2 + self.ruleset.get_dealer_cards_dealt())?;
+        if self.hand.len() > 0 || self.dealer.len() > 0 {
+            return Err(BlackjackError::AlreadyDealt);
+        }
+        let mut hand = Vec::<usize>::new();
+        let mut dealer = Vec::<usize>::new();
+        let (mut card, mut updated_deck) = draw_with(&self.deck, source)?;
+        hand.push(card);
+        (card, updated_deck) = draw_with(&updated_deck, source)?;
+        hand.push(card);
+        (card, updated_deck) = draw_with(&updated_deck, source)?;
+        dealer.push(card);
+        while dealer.len() < self.ruleset.get_dealer_cards_dealt() {
+            (card, updated_deck) = draw_with(&updated_deck, source)?;
+            dealer.push(card);
         }
+        Ok(Self {
+            hand:hand,
+            dealer:dealer,
+            stay:false,
+            deck:updated_deck,
+            cache_policy:self.cache_policy,
+            tracker:self.tracker.clone(),
+            ruleset:self.ruleset,
+            stake:Stake::SINGLE,
+        })
+    }
+
+    pub fn deal_with<S: CardSource>(&self, source:&mut S) -> Self {
+        self.try_deal_with(source).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_hit_with<S: CardSource>(&self, source:&mut S) -> Result<Self, BlackjackError> {
+        self.check_can_hit()?;
+        self.check_deck_count(1)?;
+        let mut hand = self.hand.clone();
+        let (card, updated_deck) = draw_with(&self.deck, source)?;
+        hand.push(card);
+        Ok(Self {
+            hand:hand,
+            dealer:self.dealer.clone(),
+            stay:false,
+            deck:updated_deck,
+            cache_policy:self.cache_policy,
+            tracker:self.tracker.clone(),
+            ruleset:self.ruleset,
+            stake:self.stake,
+        })
+    }
+
+    pub fn hit_with<S: CardSource>(&self, source:&mut S) -> Self {
+        self.try_hit_with(source).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_dealer_hit_with<S: CardSource>(&self, source:&mut S) -> Result<Self, BlackjackError> {
+        self.check_deck_count(1)?;
+        let mut dealer = self.dealer.clone();
+        let (card, updated_deck) = draw_with(&self.deck, source)?;
+        dealer.push(card);
+        Ok(Self {
+            hand:self.hand.clone(),
+            dealer:dealer,
+            stay:false,
+            deck:updated_deck,
+            cache_policy:self.cache_policy,
+            tracker:self.tracker.clone(),
+            ruleset:self.ruleset,
+            stake:self.stake,
+        })
+    }
+
+    pub fn dealer_hit_with<S: CardSource>(&self, source:&mut S) -> Self {
+        self.try_dealer_hit_with(source).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // doubles the bet and takes exactly one more card
+    pub fn try_double(&self) -> Result<Self, BlackjackError> {
+        self.check_can_double()?;
+        Ok(self.try_hit()?.with_stake(self.ruleset.get_double_stake(&self.hand, self.stake)))
+    }
+
+    pub fn double(&self) -> Self {
+        self.try_double().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_double_with<S: CardSource>(&self, source:&mut S) -> Result<Self, BlackjackError> {
+        self.check_can_double()?;
+        Ok(self.try_hit_with(source)?.with_stake(self.ruleset.get_double_stake(&self.hand, self.stake)))
+    }
+
+    pub fn double_with<S: CardSource>(&self, source:&mut S) -> Self {
+        self.try_double_with(source).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // the two hands of a split, each with one of the pair and a new card. Both hands see the deck
+    // with both new cards gone
+    fn get_split_hands(&self, first_card:usize, second_card:usize, deck:BlackjackDeck) -> (Self, Self) {
+        let (first_stake, second_stake) = self.ruleset.get_split_stakes(self.stake);
+        let first = Self {
+            hand:vec![self.hand[0], first_card],
+            dealer:self.dealer.clone(),
+            stay:false,
+            deck:deck.clone(),
+            cache_policy:self.cache_policy,
+            tracker:self.tracker.clone(),
+            ruleset:self.ruleset,
+            stake:first_stake,
+        };
+        let second = Self {
+            hand:vec![self.hand[1], second_card],
+            stake:second_stake,
+            deck:deck,
+            ..first.clone()
+        };
+        (first, second)
+    }
+
+    pub fn try_split(&self) -> Result<(Self, Self), BlackjackError> {
+        self.check_can_split()?;
+        self.check_deck_count(2)?;
+        let (first_card, drawn_deck) = self.deck.draw();
+        let (second_card, drawn_deck) = drawn_deck.draw();
+        Ok(self.get_split_hands(first_card, second_card, drawn_deck))
+    }
+
+    pub fn split(&self) -> (Self, Self) {
+        self.try_split().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_split_with<S: CardSource>(&self, source:&mut S) -> Result<(Self, Self), BlackjackError> {
+        self.check_can_split()?;
+        self.check_deck_count(2)?;
+        let (first_card, drawn_deck) = draw_with(&self.deck, source)?;
+        let (second_card, drawn_deck) = draw_with(&drawn_deck, source)?;
+        Ok(self.get_split_hands(first_card, second_card, drawn_deck))
+    }
+
+    pub fn split_with<S: CardSource>(&self, source:&mut S) -> (Self, Self) {
+        self.try_split_with(source).unwrap_or_else(|e| panic!("{}", e))
     }
 
     pub fn stay(&self) -> Self{
@@ -140,42 +565,60 @@ impl OptimizedBlackJackGame {
             hand:self.hand.clone(),
             dealer:self.dealer.clone(),
             stay:true,
-            deck:self.deck.clone()
+            deck:self.deck.clone(),
+            cache_policy:self.cache_policy,
+            tracker:self.tracker.clone(),
+            ruleset:self.ruleset,
+            stake:self.stake,
         }
     }
 
-    pub fn get_hand_value(&self) -> usize {
-        let mut value:usize = 0;
-        let mut aces_count:usize = 0;
-        for card in self.hand.iter(){
-            if *card == 0 {
-                aces_count += 1;
-            }
-            value += *card + 1;
-        }
-        for _i in 0..aces_count {
-            if value + 10 <= 21 {
-                value += 10;
-            }
+    // start the next round from whatever is left in the deck
+    pub fn new_round(&self) -> Self {
+        Self {
+            hand:Vec::<usize>::new(),
+            dealer:Vec::<usize>::new(),
+            stay:false,
+            deck:self.deck.clone(),
+            cache_policy:self.cache_policy,
+            tracker:self.tracker.clone(),
+            ruleset:self.ruleset,
+            stake:Stake::SINGLE,
         }
-        value
+    }
+
+    pub fn get_hand(&self) -> &Vec<usize> {
+        &self.hand
+    }
+
+    pub fn get_dealer(&self) -> &Vec<usize> {
+        &self.dealer
+    }
+
+    pub fn is_stay(&self) -> bool {
+        self.stay
+    }
+
+    pub(crate) fn get_deck(&self) -> &BlackjackDeck {
+        &self.deck
+    }
+
+    pub fn get_deck_count(&self) -> usize {
+        self.deck.count
+    }
+
+    pub fn get_deck_counts(&self) -> [usize;10] {
+        let mut counts = [0usize;10];
+        counts.clone_from_slice(&self.deck.count_by_blackjack_value);
+        counts
+    }
+
+    pub fn get_hand_value(&self) -> usize {
+        get_blackjack_value(&self.hand)
     }
 
     pub fn get_dealer_value(&self) -> usize {
-        let mut value:usize = 0;
-        let mut aces_count:usize = 0;
-        for card in self.dealer.iter(){
-            if *card == 0 {
-                aces_count += 1;
-            }
-            value += *card + 1;
-        }
-        for _i in 0..aces_count {
-            if value + 10 <= 21 {
-                value += 10;
-            }
-        }
-        value
+        get_blackjack_value(&self.dealer)
     }
 
     pub fn is_hand_bust(&self) -> bool {
@@ -191,11 +634,14 @@ impl OptimizedBlackJackGame {
     }
 
     pub fn is_dealer_blackjack(&self) -> bool {
-        self.get_dealer_value() == 21 && self.hand.len() == 2
+        self.get_dealer_value() == 21 && self.dealer.len() == 2
     }
 
-    pub fn get_hit_expected_value(&self) -> f64 {
+    // expected values come back with the number of positions solved to get them, for CachePolicy::SubtreeSize.
+    // A cache hit counts as one position
+    fn try_get_hit_expected_value_sized(&self) -> Result<(f64, usize), BlackjackError> {
         let mut expected_value:f64 = 0.0;
+        let mut subtree_size:usize = 0;
         let draw_probs = self.deck.draw_probs_by_blackjack_value();
         let mut hand_clone:Vec<usize>;                
         let mut drawn_card:usize;
@@ -213,29 +659,147 @@ impl OptimizedBlackJackGame {
                     dealer:self.dealer.clone(),
                     stay:false,
                     deck:drawn_deck,
+                    cache_policy:self.cache_policy,
+                    tracker:self.tracker.clone(),
+                    ruleset:self.ruleset,
+                    stake:self.stake,
                 };
                 //threads.push(thread::spawn(move || { return draw_probs[i] * drawn_game.get_expected_value();}));
-                expected_value += draw_probs[i] * drawn_game.get_expected_value();
+                let (drawn_ev, drawn_size) = drawn_game.try_get_expected_value_sized()?;
+                expected_value += draw_probs[i] * drawn_ev;
+                subtree_size += drawn_size;
             }
         }
         // for thread in threads {
         //     expected_value += thread.join().unwrap();
         // }
-        return expected_value;
+        return Ok((expected_value, subtree_size));
+    }
+
+    // same draws as a hit, each drawn position is doubled and can only stay or rescue
+    fn try_get_double_expected_value_sized(&self) -> Result<(f64, usize), BlackjackError> {
+        let mut expected_value:f64 = 0.0;
+        let mut subtree_size:usize = 0;
+        let draw_probs = self.deck.draw_probs_by_blackjack_value();
+        for i in 0usize..10usize {
+            if draw_probs[i] > 0.0 {
+                let (drawn_card, drawn_deck) = self.deck.draw_blackjack_value_index(i);
+                let mut hand_clone = self.hand.clone();
+                hand_clone.push(drawn_card);
+                let drawn_game = Self {
+                    hand:hand_clone,
+                    dealer:self.dealer.clone(),
+                    stay:false,
+                    deck:drawn_deck,
+                    cache_policy:self.cache_policy,
+                    tracker:self.tracker.clone(),
+                    ruleset:self.ruleset,
+                    stake:self.ruleset.get_double_stake(&self.hand, self.stake),
+                };
+                let (drawn_ev, drawn_size) = drawn_game.try_get_expected_value_sized()?;
+                expected_value += draw_probs[i] * drawn_ev;
+                subtree_size += drawn_size;
+            }
+        }
+        Ok((expected_value, subtree_size))
+    }
+
+    // a split is valued as two hands played on their own. Each one draws its second card from the
+    // deck as it is now and doesn't know what the other hand drew
+    fn try_get_split_expected_value_sized(&self) -> Result<(f64, usize), BlackjackError> {
+        let mut expected_value:f64 = 0.0;
+        let mut subtree_size:usize = 0;
+        let (first_stake, second_stake) = self.ruleset.get_split_stakes(self.stake);
+        let draw_probs = self.deck.draw_probs_by_blackjack_value();
+        for (card, stake) in [(self.hand[0], first_stake), (self.hand[1], second_stake)] {
+            for i in 0usize..10usize {
+                if draw_probs[i] > 0.0 {
+                    let (drawn_card, drawn_deck) = self.deck.draw_blackjack_value_index(i);
+                    let drawn_game = Self {
+                        hand:vec![card, drawn_card],
+                        dealer:self.dealer.clone(),
+                        stay:false,
+                        deck:drawn_deck,
+                        cache_policy:self.cache_policy,
+                        tracker:self.tracker.clone(),
+                        ruleset:self.ruleset,
+                        stake:stake,
+                    };
+                    let (drawn_ev, drawn_size) = drawn_game.try_get_expected_value_sized()?;
+                    expected_value += draw_probs[i] * drawn_ev;
+                    subtree_size += drawn_size;
+                }
+            }
+        }
+        Ok((expected_value, subtree_size))
+    }
+
+    pub fn try_get_hit_expected_value(&self) -> Result<f64, BlackjackError> {
+        self.check_can_hit()?;
+        Ok(self.try_get_hit_expected_value_sized()?.0)
+    }
+
+    pub fn get_hit_expected_value(&self) -> f64 {
+        self.try_get_hit_expected_value().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get_stay_expected_value(&self) -> Result<f64, BlackjackError> {
+        Ok(self.stay().try_get_expected_value_sized()?.0)
     }
 
     pub fn get_stay_expected_value(&self) -> f64 {
-        let stay_game = Self{
-            hand:self.hand.clone(),
-            dealer:self.dealer.clone(),
-            stay: true,
-            deck: self.deck.clone()
-        };
-        stay_game.get_expected_value()
+        self.try_get_stay_expected_value().unwrap_or_else(|e| panic!("{}", e))
     }
 
-    pub fn get_deck_expected_value(&self) -> f64 {
+    // None when the ruleset doesn't let this hand double
+    pub fn try_get_double_expected_value(&self) -> Result<Option<f64>, BlackjackError> {
+        if self.check_can_double().is_err() {
+            return Ok(None);
+        }
+        Ok(Some(self.try_get_double_expected_value_sized()?.0))
+    }
+
+    pub fn get_double_expected_value(&self) -> Option<f64> {
+        self.try_get_double_expected_value().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // None when the ruleset doesn't let this hand split
+    pub fn try_get_split_expected_value(&self) -> Result<Option<f64>, BlackjackError> {
+        if self.check_can_split().is_err() {
+            return Ok(None);
+        }
+        Ok(Some(self.try_get_split_expected_value_sized()?.0))
+    }
+
+    pub fn get_split_expected_value(&self) -> Option<f64> {
+        self.try_get_split_expected_value().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // the original bet is given up and the double comes back. None unless this is a doubled
+    // hand that hasn't stayed, under rules with rescue
+    pub fn get_rescue_expected_value(&self) -> Option<f64> {
+        if self.stay || self.is_hand_bust() {
+            return None;
+        }
+        self.ruleset.get_rescue_payout(self.stake)
+    }
+
+    pub fn try_get_action_expected_values(&self) -> Result<ActionExpectedValues, BlackjackError> {
+        Ok(ActionExpectedValues {
+            hit:self.try_get_hit_expected_value()?,
+            stay:self.try_get_stay_expected_value()?,
+            double:self.try_get_double_expected_value()?,
+            split:self.try_get_split_expected_value()?,
+        })
+    }
+
+    pub fn get_action_expected_values(&self) -> ActionExpectedValues {
+        self.try_get_action_expected_values().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_get_deck_expected_value_sized(&self) -> Result<(f64, usize), BlackjackError> {
         let mut expected_value:f64 = 0.0;
+        let mut subtree_size:usize = 0;
         let draw_probs = self.deck.draw_probs_by_blackjack_value();
         let mut dealer_clone:Vec<usize>;                
         let mut drawn_card:usize;
@@ -252,115 +816,173 @@ impl OptimizedBlackJackGame {
                     dealer:dealer_clone,
                     stay:false,
                     deck:drawn_deck,
+                    cache_policy:self.cache_policy,
+                    tracker:self.tracker.clone(),
+                    ruleset:self.ruleset,
+                    stake:self.stake,
                 };
-                threads.push(thread::spawn(move || { return draw_probs[i] * drawn_game.get_hit_expected_value();}));
+                threads.push(thread::spawn(move || -> Result<(f64, usize), BlackjackError> {
+                    // every face up dealer card comes out before the player's card
+                    let (drawn_ev, drawn_size) = if drawn_game.dealer.len() < drawn_game.ruleset.get_dealer_cards_dealt() {
+                        drawn_game.try_get_deck_expected_value_sized()?
+                    }
+                    else {
+                        drawn_game.try_get_hit_expected_value_sized()?
+                    };
+                    return Ok((draw_probs[i] * drawn_ev, drawn_size));
+                }));
                 //expected_value += draw_probs[i] * drawn_game.get_hit_expected_value();
             }
         }
         for thread in threads {
-            expected_value += thread.join().unwrap();
+            let thread_result:Result<(f64, usize), BlackjackError> = thread.join().map_err(|_| BlackjackError::SolverThreadPanicked)?;
+            let (drawn_ev, drawn_size) = thread_result?;
+            expected_value += drawn_ev;
+            subtree_size += drawn_size;
+            // each first card dealt off an undealt deck is a branch for the progress estimate
+            if let (true, Some(tracker)) = (self.dealer.is_empty(), &self.tracker) {
+                tracker.counters.add_completed_branch();
+            }
         }
-        return expected_value;
+        return Ok((expected_value, subtree_size));
     }
 
-    pub fn get_expected_value(&self) -> f64 {
+    pub fn try_get_deck_expected_value(&self) -> Result<f64, BlackjackError> {
+        Ok(self.try_get_deck_expected_value_sized()?.0)
+    }
+
+    pub fn get_deck_expected_value(&self) -> f64 {
+        self.try_get_deck_expected_value().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_get_expected_value_sized(&self) -> Result<(f64, usize), BlackjackError> {
+        if let Some(tracker) = &self.tracker {
+            if tracker.cancellation.is_cancelled() {
+                return Err(BlackjackError::Cancelled);
+            }
+            tracker.counters.add_node();
+        }
         let unique_key = self.unique_key();
-        let cached_ev = self.read_bin_file_cache(unique_key.clone());
+        let cached_ev = if self.cache_policy.should_read() { self.try_read_bin_file_cache(unique_key.clone())? } else { None };
+        if let (true, Some(tracker)) = (self.cache_policy.should_read(), &self.tracker) {
+            tracker.counters.add_memo_lookup(cached_ev.is_some());
+        }
         match cached_ev{
             Some(x) => {
                 //println!("got cached ev for {} = {} ", unique_key.clone(), x);
-                return x;
+                return Ok((x, 1));
             }
             None => {}
         }
         let now = Instant::now();
 
         // if has not been dealt, calc for each different possible deal
-        if self.hand.len() == 0 && self.dealer.len() == 0 {
-            return self.get_deck_expected_value();
+        let (expected_value, subtree_size) = if self.hand.len() == 0 && self.dealer.len() == 0 {
+            let (deck_ev, deck_size) = self.try_get_deck_expected_value_sized()?;
+            (deck_ev, deck_size + 1)
+        }
+        // a dealer blackjack dealt face up is settled once the player has both cards
+        else if self.is_dealer_blackjack() && !self.stay && self.hand.len() < 2 {
+            let (hit_ev, hit_size) = self.try_get_hit_expected_value_sized()?;
+            (hit_ev, hit_size + 1)
         }
-
         // if dealer has blackjack, you cannot win
-        if self.is_dealer_blackjack() {
+        else if self.is_dealer_blackjack() {
             // you can push though
-            if self.is_hand_blackjack() {
-                return 0.0;
-            }
-            return -1.0;
+            (self.ruleset.get_stay_payout(&self.hand, self.stake, DEALER_BLACKJACK), 1)
         }
-
-        if self.is_hand_bust() {
-            return -1.0
+        else if self.is_hand_bust() {
+            (self.stake.get_payout(-1.0), 1)
         }
-
-        if self.is_dealer_bust() {
-            if self.is_hand_blackjack() {
-                return 1.5;
-            }
-            return 1.0
+        else if self.is_dealer_bust() {
+            (self.ruleset.get_stay_payout(&self.hand, self.stake, get_dealer_bust_outcome(self.get_dealer_value())), 1)
         }
-
         // if stay, check for payouts
-        if self.stay {
-            if self.get_dealer_value() > self.get_hand_value() {
-                return -1.0
-            }
-
+        else if self.stay {
             // dealer stays on >=17
             if self.get_dealer_value() > 16 {
-                if self.is_hand_blackjack() && !self.is_dealer_blackjack(){
-                    return 1.5;
-                }
-                if self.get_dealer_value() == self.get_hand_value() {
-                    return 0.0;
-                }
-                else if self.get_dealer_value() > self.get_hand_value() {
-                    return -1.0;
-                }
-                else if self.get_dealer_value() < self.get_hand_value() {
-                    return 1.0;
-                }
+                (self.ruleset.get_stay_payout(&self.hand, self.stake, self.get_dealer_value() - 17), 1)
             }
             else {
-                let mut expected_value:f64 = 0.0;
-                let draw_probs = self.deck.draw_probs_by_blackjack_value();
-                let mut dealer_clone:Vec<usize>;                
-                let mut drawn_card:usize;
-                let mut drawn_deck:BlackjackDeck;
-                let mut drawn_game:Self;
-                for i in 0usize..10usize {
-                    if draw_probs[i] > 0.0 {
-                        (drawn_card, drawn_deck) = self.deck.draw_blackjack_value_index(i);
-                        dealer_clone = self.dealer.clone();
-                        dealer_clone.push(drawn_card);
-                        drawn_game = Self {
-                            hand:self.hand.clone(),
-                            dealer:dealer_clone,
-                            stay:true,
-                            deck:drawn_deck,
-                        };
-                        expected_value += draw_probs[i] * drawn_game.get_expected_value();
-                    }
-                }
-                if now.elapsed().as_secs() > 1 {
-                    //println!("caching {} = {}, it took {} seconds to complete", unique_key.clone(), expected_value,  now.elapsed().as_secs());
-                    self.write_bin_file_cache(unique_key, expected_value);
-                }
-                return expected_value;
+                // the dealer draws the same way whatever the hand is, so the outcome distribution
+                // comes from the shared table instead of recursing through every dealer draw
+                let outcome_probs = DealerOutcomeTable::shared().get_outcome_probs(&self.dealer, &self.deck);
+                (self.ruleset.get_stay_expected_value(&self.hand, self.stake, &outcome_probs), 1)
             }
         }
+        // a doubled hand stays, or gives up the original bet if the rules have rescue
+        else if self.stake.doubled {
+            let (stay_ev, stay_size) = self.stay().try_get_expected_value_sized()?;
+            (f64::max(stay_ev, self.get_rescue_expected_value().unwrap_or(f64::NEG_INFINITY)), stay_size + 1)
+        }
         else {
             //if not stay, determine expected value for hit or stay, return > value
-            let stay_ev = self.get_stay_expected_value();
-            let hit_ev =self.get_hit_expected_value();
-            let expected_value = f64::max(stay_ev, hit_ev);
-            if now.elapsed().as_secs() > 1 {
-                //println!("caching {} = {}, it took {} seconds to complete", unique_key.clone(), expected_value, now.elapsed().as_secs());
-                self.write_bin_file_cache(unique_key.clone(), expected_value);
+            let (stay_ev, stay_size) = self.stay().try_get_expected_value_sized()?;
+            let (hit_ev, hit_size) = self.try_get_hit_expected_value_sized()?;
+            let (mut expected_value, mut subtree_size) = (f64::max(stay_ev, hit_ev), stay_size + hit_size + 1);
+            if self.ruleset.can_double(&self.hand) {
+                let (double_ev, double_size) = self.try_get_double_expected_value_sized()?;
+                expected_value = f64::max(expected_value, double_ev);
+                subtree_size += double_size;
+            }
+            if self.check_can_split().is_ok() {
+                let (split_ev, split_size) = self.try_get_split_expected_value_sized()?;
+                expected_value = f64::max(expected_value, split_ev);
+                subtree_size += split_size;
             }
-            return expected_value;
+            (expected_value, subtree_size)
+        };
+
+        if self.cache_policy.should_cache(now.elapsed(), subtree_size, self.hand.len() + self.dealer.len()) {
+            //println!("caching {} = {}, it took {} seconds to complete", unique_key.clone(), expected_value, now.elapsed().as_secs());
+            self.try_write_bin_file_cache(unique_key, expected_value)?;
+        }
+        Ok((expected_value, subtree_size))
+    }
+
+    pub fn try_get_expected_value(&self) -> Result<f64, BlackjackError> {
+        Ok(self.try_get_expected_value_sized()?.0)
+    }
+
+    pub fn get_expected_value(&self) -> f64 {
+        self.try_get_expected_value().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // try_get_expected_value with callback called every interval from a monitor thread, and once
+    // more at the end. An undealt game has a branch per first card dealt, anything else is one
+    // branch. Memo lookups count reads of the bin/ cache
+    pub fn try_get_expected_value_with_progress<F: Fn(&SolverProgress) + Sync>(&self, interval:Duration, callback:F) -> Result<f64, BlackjackError> {
+        let undealt = self.hand.is_empty() && self.dealer.is_empty();
+        let total_branches = if undealt { self.deck.draw_probs_by_blackjack_value().iter().filter(|x| **x > 0.0).count() } else { 1 };
+        let counters = Arc::new(ProgressCounters::new(total_branches));
+        let mut game = self.clone();
+        game.tracker = Some(QueryTracker {
+            cancellation:self.tracker.as_ref().map(|x| x.cancellation.clone()).unwrap_or_default(),
+            counters:counters.clone(),
+        });
+        let now = Instant::now();
+        let done = AtomicBool::new(false);
+        let result = thread::scope(|s| {
+            s.spawn(|| {
+                let step = interval.min(Duration::from_millis(20));
+                let mut last_report = Instant::now();
+                while !done.load(Ordering::Relaxed) {
+                    thread::sleep(step);
+                    if last_report.elapsed() >= interval {
+                        callback(&counters.snapshot(now.elapsed()));
+                        last_report = Instant::now();
+                    }
+                }
+            });
+            let result = game.try_get_expected_value();
+            done.store(true, Ordering::Relaxed);
+            result
+        });
+        if result.is_ok() && !undealt {
+            counters.add_completed_branch();
         }
-        panic!("THIS SHOULD NEVER BE REACHED")
+        callback(&counters.snapshot(now.elapsed()));
+        result
     }
 
     pub fn print_contents(&self) {
@@ -0,0 +1,498 @@
+use game_pieces_rs::blackjack_deck::BlackjackDeck;
+
+use crate::action::ActionExpectedValues;
+use crate::dealer_table::{get_stay_expected_value, DealerOutcomeTable, DEALER_OUTCOMES};
+use crate::error::BlackjackError;
+use crate::opt_bj_game::{get_blackjack_value, OptimizedBlackJackGame};
+use crate::progress::{CancellationToken, ProgressCounters, SolverProgress};
+
+use dashmap::DashMap;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// positions with at most this many cards dealt get split into parallel tasks, anything deeper
+// runs serially on whichever thread picked it up
+pub const DEFAULT_PARALLEL_DEPTH:usize = 3;
+
+type ProgressCallback = Box<dyn Fn(&SolverProgress) + Send + Sync>;
+
+// same game and answers as OptimizedBlackJackGame::get_expected_value, but the deals, hit draws
+// and dealer draws are split across a rayon work stealing pool instead of one OS thread per
+// upcard. Expected values are memoised in memory, shared by every thread in the pool and by
+// every query. Each query counts its own progress
+pub struct ParallelSolver {
+    pool: ThreadPool,
+    memo: DashMap<String, f64>,
+    // once the memo holds this many entries it is emptied and starts again
+    max_memo_len: usize,
+    // the solver's own, so it is dropped with the solver instead of growing the shared one
+    dealer_outcomes: DealerOutcomeTable,
+    parallel_depth: usize,
+    cancellation: CancellationToken,
+    // the counters of the query that started last
+    last_counters: Mutex<Arc<ProgressCounters>>,
+    progress_callback: Option<(Duration, ProgressCallback)>,
+    checkpoint: Option<(PathBuf, Duration)>,
+    // memo entries not yet appended to the checkpoint
+    pending_checkpoint: Mutex<Vec<(String, f64)>>,
+}
+
+// what one query carries down the recursion
+struct Query<'a> {
+    cancellation: &'a CancellationToken,
+    counters: &'a ProgressCounters,
+}
+
+fn get_checkpoint_io_error(path:&Path, source:io::Error) -> BlackjackError {
+    BlackjackError::CheckpointIo {
+        path:path.display().to_string(),
+        source:source,
+    }
+}
+
+// "<key> <f64 bits as 16 hex digits>", None for anything else
+fn parse_checkpoint_line(line:&str) -> Option<(String, f64)> {
+    let (key, bits) = line.split_once(' ')?;
+    if key.is_empty() || bits.len() != 16 {
+        return None;
+    }
+    let bits = u64::from_str_radix(bits, 16).ok()?;
+    Some((key.to_string(), f64::from_bits(bits)))
+}
+
+impl ParallelSolver {
+    // num_threads caps the pool size, 0 means one thread per core
+    pub fn new(num_threads:usize) -> Result<Self, BlackjackError> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(|i| format!("blackjack-solver-{}", i))
+            .build()
+            .map_err(|e| BlackjackError::ThreadPool(e.to_string()))?;
+        Ok(Self {
+            pool:pool,
+            memo:DashMap::new(),
+            max_memo_len:usize::MAX,
+            dealer_outcomes:DealerOutcomeTable::new(),
+            parallel_depth:DEFAULT_PARALLEL_DEPTH,
+            cancellation:CancellationToken::new(),
+            last_counters:Mutex::new(Arc::new(ProgressCounters::default())),
+            progress_callback:None,
+            checkpoint:None,
+            pending_checkpoint:Mutex::new(Vec::<(String, f64)>::new()),
+        })
+    }
+
+    pub fn with_parallel_depth(mut self, parallel_depth:usize) -> Self {
+        self.parallel_depth = parallel_depth;
+        self
+    }
+
+    // unbounded by default. A long running process, like the http server, should set one, every
+    // entry is a key of around 50 bytes and an f64
+    pub fn with_max_memo_len(mut self, max_memo_len:usize) -> Self {
+        self.max_memo_len = max_memo_len;
+        self
+    }
+
+    // cancel the token from any thread and the running query returns BlackjackError::Cancelled.
+    // The token stays cancelled until it is reset
+    pub fn with_cancellation_token(mut self, cancellation:CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    // called every interval from a monitor thread while a query runs, and once more at the end.
+    // To get a channel instead, send from the callback
+    pub fn with_progress_callback<F: Fn(&SolverProgress) + Send + Sync + 'static>(mut self, interval:Duration, callback:F) -> Self {
+        self.progress_callback = Some((interval, Box::new(callback)));
+        self
+    }
+
+    // appends every newly finished memo entry to path each interval, and when a query ends or is
+    // cancelled, so a killed run loses at most one interval of work. Use resume_from_checkpoint
+    // to load it back. Dealer outcomes aren't saved, they are cheap to rebuild
+    pub fn with_checkpoint<P: AsRef<Path>>(mut self, path:P, interval:Duration) -> Self {
+        self.checkpoint = Some((path.as_ref().to_path_buf(), interval));
+        self
+    }
+
+    pub fn get_num_threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    pub fn get_parallel_depth(&self) -> usize {
+        self.parallel_depth
+    }
+
+    pub fn get_cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    // counters for the query that started last, the progress callback gets each query's own
+    pub fn get_progress(&self, elapsed:Duration) -> SolverProgress {
+        self.last_counters.lock().unwrap_or_else(|e| e.into_inner()).snapshot(elapsed)
+    }
+
+    pub fn get_memo_len(&self) -> usize {
+        self.memo.len()
+    }
+
+    pub fn get_max_memo_len(&self) -> usize {
+        self.max_memo_len
+    }
+
+    pub fn clear_memo(&self) {
+        self.memo.clear();
+        self.dealer_outcomes.clear();
+    }
+
+    fn remember(&self, unique_key:String, expected_value:f64) {
+        if self.checkpoint.is_some() {
+            self.pending_checkpoint.lock().unwrap_or_else(|e| e.into_inner()).push((unique_key.clone(), expected_value));
+        }
+        if self.memo.len() >= self.max_memo_len {
+            self.memo.clear();
+        }
+        self.memo.insert(unique_key, expected_value);
+    }
+
+    // appends the pending entries to the checkpoint file, returns how many were written
+    pub fn try_write_checkpoint(&self) -> Result<usize, BlackjackError> {
+        let path = match &self.checkpoint {
+            Some((path, _)) => path,
+            None => return Ok(0),
+        };
+        let mut pending = self.pending_checkpoint.lock().unwrap_or_else(|e| e.into_inner());
+        if pending.is_empty() {
+            return Ok(0);
+        }
+        let mut contents = String::new();
+        for (key, expected_value) in pending.iter() {
+            contents.push_str(&format!("{} {:016x}\n", key, expected_value.to_bits()));
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| get_checkpoint_io_error(path, e))?;
+        file.write_all(contents.as_bytes()).map_err(|e| get_checkpoint_io_error(path, e))?;
+        file.sync_data().map_err(|e| get_checkpoint_io_error(path, e))?;
+        let written = pending.len();
+        pending.clear();
+        Ok(written)
+    }
+
+    // loads a checkpoint into the memo, returns how many entries it had. A missing file is an
+    // empty checkpoint. A torn last line from a killed run is dropped and cut off the file, so
+    // appending can carry on after it
+    pub fn resume_from_checkpoint<P: AsRef<Path>>(&self, path:P) -> Result<usize, BlackjackError> {
+        let path = path.as_ref();
+        let contents = match fs::read_to_string(path) {
+            Ok(x) => x,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(get_checkpoint_io_error(path, e)),
+        };
+        let mut loaded = 0;
+        let mut valid_len = 0;
+        let lines = contents.split_inclusive('\n').collect::<Vec<&str>>();
+        for (i, line) in lines.iter().enumerate() {
+            let entry = line.strip_suffix('\n').and_then(parse_checkpoint_line);
+            match entry {
+                Some((key, expected_value)) => {
+                    self.memo.insert(key, expected_value);
+                    loaded += 1;
+                    valid_len += line.len();
+                }
+                None if i + 1 == lines.len() => {
+                    let file = OpenOptions::new().write(true).open(path).map_err(|e| get_checkpoint_io_error(path, e))?;
+                    file.set_len(valid_len as u64).map_err(|e| get_checkpoint_io_error(path, e))?;
+                }
+                None => {
+                    return Err(BlackjackError::CorruptCheckpoint {
+                        path:path.display().to_string(),
+                        line:i + 1,
+                    });
+                }
+            }
+        }
+        Ok(loaded)
+    }
+
+    // reports progress and writes checkpoints until the query is done. A failed write keeps its
+    // entries pending and is tried again next interval, run returns the error if the last one fails
+    fn monitor(&self, counters:&ProgressCounters, now:Instant, done:&AtomicBool) {
+        let mut step = Duration::from_millis(20);
+        if let Some((interval, _)) = &self.progress_callback {
+            step = step.min(*interval);
+        }
+        if let Some((_, interval)) = &self.checkpoint {
+            step = step.min(*interval);
+        }
+        let mut last_report = Instant::now();
+        let mut last_checkpoint = Instant::now();
+        while !done.load(Ordering::Relaxed) {
+            thread::sleep(step);
+            if let Some((interval, callback)) = &self.progress_callback {
+                if last_report.elapsed() >= *interval {
+                    callback(&counters.snapshot(now.elapsed()));
+                    last_report = Instant::now();
+                }
+            }
+            if let Some((_, interval)) = &self.checkpoint {
+                if last_checkpoint.elapsed() >= *interval {
+                    self.try_write_checkpoint().ok();
+                    last_checkpoint = Instant::now();
+                }
+            }
+        }
+    }
+
+    // runs a query on the pool, with the monitor thread reporting progress and checkpointing alongside it
+    fn run<T: Send, F: FnOnce(&Query) -> Result<T, BlackjackError> + Send>(&self, total_branches:usize, cancellation:&CancellationToken, query:F) -> Result<T, BlackjackError> {
+        let counters = Arc::new(ProgressCounters::new(total_branches));
+        *self.last_counters.lock().unwrap_or_else(|e| e.into_inner()) = counters.clone();
+        let query_state = Query {
+            cancellation:cancellation,
+            counters:&counters,
+        };
+        let now = Instant::now();
+        let done = AtomicBool::new(false);
+        thread::scope(|s| {
+            let monitor = if self.progress_callback.is_some() || self.checkpoint.is_some() {
+                Some(s.spawn(|| self.monitor(&counters, now, &done)))
+            }
+            else {
+                None
+            };
+            let result = self.pool.install(|| query(&query_state));
+            done.store(true, Ordering::Relaxed);
+            if let Some(monitor) = monitor {
+                monitor.join().map_err(|_| BlackjackError::SolverThreadPanicked)?;
+            }
+            // checkpoint whatever finished, even if the query was cancelled
+            let checkpointed = self.try_write_checkpoint();
+            if let Some((_, callback)) = &self.progress_callback {
+                callback(&counters.snapshot(now.elapsed()));
+            }
+            checkpointed?;
+            result
+        })
+    }
+
+    fn get_total_branches(game:&OptimizedBlackJackGame) -> usize {
+        if !game.get_hand().is_empty() || !game.get_dealer().is_empty() {
+            return 1;
+        }
+        Self::get_draws(game.get_deck()).iter().map(|(_, i)| {
+            let (_, drawn_deck) = game.get_deck().draw_blackjack_value_index(*i);
+            Self::get_draws(&drawn_deck).len()
+        }).sum()
+    }
+
+    // the recursion here plays standard hit or stay, other rulesets go through OptimizedBlackJackGame
+    fn check_ruleset(game:&OptimizedBlackJackGame) -> Result<(), BlackjackError> {
+        if !game.get_ruleset().is_standard() || !game.get_stake().is_single() {
+            return Err(BlackjackError::UnsupportedRuleset(format!("the parallel solver only plays standard rules, not {}", game.get_ruleset())));
+        }
+        Ok(())
+    }
+
+    pub fn try_get_expected_value(&self, game:&OptimizedBlackJackGame) -> Result<f64, BlackjackError> {
+        Self::check_ruleset(game)?;
+        self.run(Self::get_total_branches(game), &self.cancellation, |query| {
+            let expected_value = self.solve(game, query)?;
+            if !game.get_hand().is_empty() || !game.get_dealer().is_empty() {
+                query.counters.add_completed_branch();
+            }
+            Ok(expected_value)
+        })
+    }
+
+    pub fn get_expected_value(&self, game:&OptimizedBlackJackGame) -> f64 {
+        self.try_get_expected_value(game).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get_hit_expected_value(&self, game:&OptimizedBlackJackGame) -> Result<f64, BlackjackError> {
+        Self::check_ruleset(game)?;
+        self.run(1, &self.cancellation, |query| {
+            let expected_value = self.solve_hit(game, false, query)?;
+            query.counters.add_completed_branch();
+            Ok(expected_value)
+        })
+    }
+
+    pub fn get_hit_expected_value(&self, game:&OptimizedBlackJackGame) -> f64 {
+        self.try_get_hit_expected_value(game).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get_stay_expected_value(&self, game:&OptimizedBlackJackGame) -> Result<f64, BlackjackError> {
+        self.try_get_expected_value(&game.stay())
+    }
+
+    pub fn get_stay_expected_value(&self, game:&OptimizedBlackJackGame) -> f64 {
+        self.try_get_stay_expected_value(game).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get_action_expected_values(&self, game:&OptimizedBlackJackGame) -> Result<ActionExpectedValues, BlackjackError> {
+        self.try_get_action_expected_values_with_token(game, &self.cancellation)
+    }
+
+    // also stops when cancellation is cancelled, so concurrent queries sharing the solver and its
+    // memo can each have their own timeout
+    // double and split always come back None: they are not computed, the recursion only plays
+    // hit or stay
+    pub fn try_get_action_expected_values_with_token(&self, game:&OptimizedBlackJackGame, cancellation:&CancellationToken) -> Result<ActionExpectedValues, BlackjackError> {
+        Self::check_ruleset(game)?;
+        self.run(1, cancellation, |query| {
+            let (hit, stay) = rayon::join(|| self.solve_hit(game, false, query), || self.solve(&game.stay(), query));
+            query.counters.add_completed_branch();
+            Ok(ActionExpectedValues {
+                hit:hit?,
+                stay:stay?,
+                double:None,
+                split:None,
+            })
+        })
+    }
+
+    pub fn get_action_expected_values(&self, game:&OptimizedBlackJackGame) -> ActionExpectedValues {
+        self.try_get_action_expected_values(game).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn is_parallel(&self, hand:&[usize], dealer:&[usize]) -> bool {
+        hand.len() + dealer.len() <= self.parallel_depth
+    }
+
+    // the solver's own token or the one for this query
+    fn check_cancelled(&self, query:&Query) -> Result<(), BlackjackError> {
+        if self.cancellation.is_cancelled() || query.cancellation.is_cancelled() {
+            return Err(BlackjackError::Cancelled);
+        }
+        Ok(())
+    }
+
+    // (draw probability, index) for every card value still in the deck
+    fn get_draws(deck:&BlackjackDeck) -> Vec<(f64, usize)> {
+        let draw_probs = deck.draw_probs_by_blackjack_value();
+        (0usize..10usize).filter(|i| draw_probs[*i] > 0.0).map(|i| (draw_probs[i], i)).collect::<Vec<(f64, usize)>>()
+    }
+
+    // sums in draw order whether or not the terms came back in parallel, so the answer doesn't
+    // depend on the thread count
+    fn sum_draws<F: Fn(usize) -> Result<f64, BlackjackError> + Sync>(&self, deck:&BlackjackDeck, parallel:bool, get_ev:F) -> Result<f64, BlackjackError> {
+        let draws = Self::get_draws(deck);
+        let evs = if parallel {
+            draws.par_iter().map(|(prob, i)| Ok(prob * get_ev(*i)?)).collect::<Result<Vec<f64>, BlackjackError>>()?
+        }
+        else {
+            draws.iter().map(|(prob, i)| Ok(prob * get_ev(*i)?)).collect::<Result<Vec<f64>, BlackjackError>>()?
+        };
+        Ok(evs.iter().sum())
+    }
+
+    fn solve(&self, game:&OptimizedBlackJackGame, query:&Query) -> Result<f64, BlackjackError> {
+        self.check_cancelled(query)?;
+        query.counters.add_node();
+
+        // if has not been dealt, calc for each different possible deal
+        if game.get_hand().is_empty() && game.get_dealer().is_empty() {
+            return self.solve_deck(game, query);
+        }
+
+        // if dealer has blackjack, you cannot win
+        if game.is_dealer_blackjack() {
+            // you can push though
+            if game.is_hand_blackjack() {
+                return Ok(0.0);
+            }
+            return Ok(-1.0);
+        }
+        if game.is_hand_bust() {
+            return Ok(-1.0);
+        }
+        if game.is_dealer_bust() {
+            if game.is_hand_blackjack() {
+                return Ok(1.5);
+            }
+            return Ok(1.0);
+        }
+
+        let unique_key = game.unique_key();
+        let cached_ev = self.memo.get(&unique_key).map(|x| *x);
+        query.counters.add_memo_lookup(cached_ev.is_some());
+        if let Some(x) = cached_ev {
+            return Ok(x);
+        }
+        let expected_value = if game.is_stay() {
+            let outcome_probs = self.get_dealer_outcome_probs(game.get_hand(), game.get_dealer(), game.get_deck(), query)?;
+            get_stay_expected_value(game.get_hand_value(), game.is_hand_blackjack(), &outcome_probs)
+        }
+        else {
+            let (stay_ev, hit_ev) = if self.is_parallel(game.get_hand(), game.get_dealer()) {
+                rayon::join(|| self.solve(&game.stay(), query), || self.solve_hit(game, false, query))
+            }
+            else {
+                (self.solve(&game.stay(), query), self.solve_hit(game, false, query))
+            };
+            f64::max(stay_ev?, hit_ev?)
+        };
+        self.remember(unique_key, expected_value);
+        Ok(expected_value)
+    }
+
+    // top_level counts each drawn card as a completed branch for the progress estimate
+    fn solve_hit(&self, game:&OptimizedBlackJackGame, top_level:bool, query:&Query) -> Result<f64, BlackjackError> {
+        let parallel = self.is_parallel(game.get_hand(), game.get_dealer());
+        self.sum_draws(game.get_deck(), parallel, |i| {
+            let (drawn_card, drawn_deck) = game.get_deck().draw_blackjack_value_index(i);
+            let mut hand_clone = game.get_hand().clone();
+            hand_clone.push(drawn_card);
+            let expected_value = self.solve(&OptimizedBlackJackGame::from_parts(hand_clone, game.get_dealer().clone(), false, drawn_deck), query)?;
+            if top_level {
+                query.counters.add_completed_branch();
+            }
+            Ok(expected_value)
+        })
+    }
+
+    // same deal order as OptimizedBlackJackGame::get_deck_expected_value, dealer card then one player card
+    fn solve_deck(&self, game:&OptimizedBlackJackGame, query:&Query) -> Result<f64, BlackjackError> {
+        self.sum_draws(game.get_deck(), true, |i| {
+            let (drawn_card, drawn_deck) = game.get_deck().draw_blackjack_value_index(i);
+            let mut dealer_clone = game.get_dealer().clone();
+            dealer_clone.push(drawn_card);
+            self.solve_hit(&OptimizedBlackJackGame::from_parts(game.get_hand().clone(), dealer_clone, false, drawn_deck), true, query)
+        })
+    }
+
+    // the first level of dealer draws is split across the pool, below that the table recurses serially
+    fn get_dealer_outcome_probs(&self, hand:&[usize], dealer:&[usize], deck:&BlackjackDeck, query:&Query) -> Result<[f64;DEALER_OUTCOMES], BlackjackError> {
+        let table = &self.dealer_outcomes;
+        if !self.is_parallel(hand, dealer) || get_blackjack_value(dealer) > 16 {
+            return Ok(table.get_outcome_probs(dealer, deck));
+        }
+        if let Some(x) = table.get(dealer, deck) {
+            return Ok(x);
+        }
+        let draws = Self::get_draws(deck);
+        let drawn_outcomes = draws.par_iter().map(|(prob, i)| {
+            self.check_cancelled(query)?;
+            let (drawn_card, drawn_deck) = deck.draw_blackjack_value_index(*i);
+            let mut dealer_clone = dealer.to_vec();
+            dealer_clone.push(drawn_card);
+            Ok((*prob, table.get_outcome_probs(&dealer_clone, &drawn_deck)))
+        }).collect::<Result<Vec<(f64, [f64;DEALER_OUTCOMES])>, BlackjackError>>()?;
+        let mut outcomes = [0.0f64;DEALER_OUTCOMES];
+        for (prob, drawn) in drawn_outcomes.iter() {
+            for j in 0..DEALER_OUTCOMES {
+                outcomes[j] += prob * drawn[j];
+            }
+        }
+        table.insert(dealer, deck, outcomes);
+        Ok(outcomes)
+    }
+}
@@ -0,0 +1,87 @@
+use crate::shoe::{Shoe, ShuffleMode};
+use crate::table::{BetPolicy, Seat, SeatResult, Strategy, Table};
+
+// flat betting ev at one cut card position. The cut card effect is the difference between
+// dealing to the cut card and dealing the same average number of rounds from every shoe:
+// rounds heavy in small cards use up more of the shoe, so fewer rounds get played from shoes
+// that are rich in small cards and the player does slightly worse
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PenetrationResult {
+    pub penetration: f64,
+    pub rounds_per_shoe: f64,
+    pub cut_card_expected_value: f64,
+    pub cut_card_standard_error: f64,
+    pub fixed_rounds_expected_value: f64,
+    pub fixed_rounds_standard_error: f64,
+}
+
+impl PenetrationResult {
+    pub fn get_cut_card_effect(&self) -> f64 {
+        self.cut_card_expected_value - self.fixed_rounds_expected_value
+    }
+}
+
+pub struct PenetrationAnalysis {
+    pub num_decks: usize,
+    pub burn_cards: usize,
+    pub strategy: Strategy,
+    pub rounds: usize,
+    // continuous shuffling machine, the no-penetration baseline
+    pub continuous_expected_value: f64,
+    pub continuous_standard_error: f64,
+    pub results: Vec<PenetrationResult>,
+}
+
+fn simulate_single_seat(shoe:Shoe, strategy:Strategy, rounds:usize) -> (SeatResult, f64) {
+    let mut table = Table::with_shoe(shoe, vec![Seat::new(strategy, BetPolicy::Flat(1.0))]);
+    table.simulate(rounds);
+    (table.get_seat_results()[0], table.get_rounds_per_shoe())
+}
+
+pub fn analyse_penetration(num_decks:usize, burn_cards:usize, penetrations:&[f64], strategy:Strategy, rounds:usize) -> PenetrationAnalysis {
+    let (continuous_result, _) = simulate_single_seat(Shoe::new_continuous(num_decks), strategy, rounds);
+    let mut results = Vec::<PenetrationResult>::new();
+    for penetration in penetrations.iter() {
+        let (cut_card_result, rounds_per_shoe) = simulate_single_seat(Shoe::new_cut_card(num_decks, *penetration, burn_cards), strategy, rounds);
+        let fixed_rounds = usize::max(rounds_per_shoe.round() as usize, 1);
+        let fixed_shoe = Shoe::new(num_decks, ShuffleMode::FixedRounds { rounds:fixed_rounds, burn_cards:burn_cards });
+        let (fixed_rounds_result, _) = simulate_single_seat(fixed_shoe, strategy, rounds);
+        results.push(PenetrationResult {
+            penetration:*penetration,
+            rounds_per_shoe:rounds_per_shoe,
+            cut_card_expected_value:cut_card_result.get_expected_value_per_hand(),
+            cut_card_standard_error:cut_card_result.get_standard_error(),
+            fixed_rounds_expected_value:fixed_rounds_result.get_expected_value_per_hand(),
+            fixed_rounds_standard_error:fixed_rounds_result.get_standard_error(),
+        });
+    }
+    PenetrationAnalysis {
+        num_decks:num_decks,
+        burn_cards:burn_cards,
+        strategy:strategy,
+        rounds:rounds,
+        continuous_expected_value:continuous_result.get_expected_value_per_hand(),
+        continuous_standard_error:continuous_result.get_standard_error(),
+        results:results,
+    }
+}
+
+impl PenetrationAnalysis {
+    pub fn print_contents(&self) {
+        println!("Decks: {}, Burn cards: {}, Strategy: {:?}, Rounds per point: {}", self.num_decks, self.burn_cards, self.strategy, self.rounds);
+        println!("CSM EV: {:+.5} (+/- {:.5})", self.continuous_expected_value, self.continuous_standard_error);
+        println!("penetration, rounds per shoe, cut card EV, fixed rounds EV, cut card effect");
+        for result in self.results.iter() {
+            println!(
+                "{:.3}, {:.2}, {:+.5} (+/- {:.5}), {:+.5} (+/- {:.5}), {:+.5}",
+                result.penetration,
+                result.rounds_per_shoe,
+                result.cut_card_expected_value,
+                result.cut_card_standard_error,
+                result.fixed_rounds_expected_value,
+                result.fixed_rounds_standard_error,
+                result.get_cut_card_effect(),
+            );
+        }
+    }
+}
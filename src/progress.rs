@@ -0,0 +1,118 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// shared flag a long computation checks as it goes, cancel from any thread to stop it
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self {
+            cancelled:Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+// snapshot of a running computation
+#[derive(Debug, Clone, Copy)]
+pub struct SolverProgress {
+    pub nodes_explored: usize,
+    pub memo_lookups: usize,
+    pub memo_hits: usize,
+    // top level branches, one per (dealer card, player card) deal for an undealt deck
+    pub completed_branches: usize,
+    pub total_branches: usize,
+    pub elapsed: Duration,
+}
+
+impl SolverProgress {
+    pub fn get_cache_hit_rate(&self) -> f64 {
+        if self.memo_lookups == 0 {
+            return 0.0;
+        }
+        self.memo_hits as f64 / self.memo_lookups as f64
+    }
+
+    pub fn get_fraction_complete(&self) -> f64 {
+        if self.total_branches == 0 {
+            return 0.0;
+        }
+        self.completed_branches as f64 / self.total_branches as f64
+    }
+
+    // extrapolates from the branches done so far. Later branches hit the memo more often, so
+    // this tends to overestimate
+    pub fn get_estimated_remaining(&self) -> Option<Duration> {
+        if self.completed_branches == 0 || self.total_branches == 0 {
+            return None;
+        }
+        let remaining = (self.total_branches - self.completed_branches) as f64 / self.completed_branches as f64;
+        Some(self.elapsed.mul_f64(remaining))
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ProgressCounters {
+    nodes_explored: AtomicUsize,
+    memo_lookups: AtomicUsize,
+    memo_hits: AtomicUsize,
+    completed_branches: AtomicUsize,
+    total_branches: AtomicUsize,
+}
+
+impl ProgressCounters {
+    pub(crate) fn new(total_branches:usize) -> Self {
+        Self {
+            total_branches:AtomicUsize::new(total_branches),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn add_node(&self) {
+        self.nodes_explored.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_memo_lookup(&self, hit:bool) {
+        self.memo_lookups.fetch_add(1, Ordering::Relaxed);
+        if hit {
+            self.memo_hits.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn add_completed_branch(&self) {
+        self.completed_branches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self, elapsed:Duration) -> SolverProgress {
+        SolverProgress {
+            nodes_explored:self.nodes_explored.load(Ordering::Relaxed),
+            memo_lookups:self.memo_lookups.load(Ordering::Relaxed),
+            memo_hits:self.memo_hits.load(Ordering::Relaxed),
+            completed_branches:self.completed_branches.load(Ordering::Relaxed),
+            total_branches:self.total_branches.load(Ordering::Relaxed),
+            elapsed:elapsed,
+        }
+    }
+}
+
+// the token and counters a query on OptimizedBlackJackGame carries to every position it reaches
+#[derive(Clone, Default)]
+pub(crate) struct QueryTracker {
+    pub(crate) cancellation: CancellationToken,
+    pub(crate) counters: Arc<ProgressCounters>,
+}
@@ -0,0 +1,301 @@
+use crate::action::ActionExpectedValues;
+use crate::cache_policy::CachePolicy;
+use crate::card_source::RngSource;
+use crate::chart::{get_chart_columns, StrategyChart, UPCARD_ORDER};
+use crate::infinite_deck::InfiniteDeckEngine;
+use crate::opt_bj_game::OptimizedBlackJackGame;
+use crate::ruleset::Ruleset;
+use crate::validation::{simulate_expected_value_with, try_cross_validate};
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+create_exception!(blackjack_rs, BlackjackError, PyException);
+
+impl From<crate::error::BlackjackError> for PyErr {
+    fn from(error:crate::error::BlackjackError) -> Self {
+        BlackjackError::new_err(error.to_string())
+    }
+}
+
+#[pyclass(name = "ActionExpectedValues", get_all, frozen)]
+#[derive(Clone, Copy)]
+pub struct PyActionExpectedValues {
+    hit: f64,
+    stay: f64,
+    double: Option<f64>,
+    split: Option<f64>,
+}
+
+impl From<ActionExpectedValues> for PyActionExpectedValues {
+    fn from(action_evs:ActionExpectedValues) -> Self {
+        Self {
+            hit:action_evs.hit,
+            stay:action_evs.stay,
+            double:action_evs.double,
+            split:action_evs.split,
+        }
+    }
+}
+
+impl PyActionExpectedValues {
+    fn get_action_evs(&self) -> ActionExpectedValues {
+        ActionExpectedValues {
+            hit:self.hit,
+            stay:self.stay,
+            double:self.double,
+            split:self.split,
+        }
+    }
+}
+
+#[pymethods]
+impl PyActionExpectedValues {
+    fn best_action(&self) -> String {
+        self.get_action_evs().best_action().to_string()
+    }
+
+    fn best_expected_value(&self) -> f64 {
+        self.get_action_evs().get_best_expected_value()
+    }
+
+    fn __repr__(&self) -> String {
+        let mut repr = format!("ActionExpectedValues(hit={}, stay={}", self.hit, self.stay);
+        if let Some(x) = self.double {
+            repr += &format!(", double={}", x);
+        }
+        if let Some(x) = self.split {
+            repr += &format!(", split={}", x);
+        }
+        repr + ")"
+    }
+}
+
+// one row per chart hand: label, representative hand, one cell per chart column
+type PyChartRow = (String, Vec<usize>, Vec<Option<PyActionExpectedValues>>);
+
+fn get_chart_rows(chart:StrategyChart) -> Vec<PyChartRow> {
+    chart.rows.into_iter().map(|row| {
+        let cells = row.cells.into_iter().map(|x| x.map(PyActionExpectedValues::from)).collect::<Vec<Option<PyActionExpectedValues>>>();
+        (row.label, row.hand, cells)
+    }).collect::<Vec<PyChartRow>>()
+}
+
+// OptimizedBlackJackGame for python. Cards are blackjack value indices, 0 is the ace and 9 the
+// ten values. Every action returns a new game, solves release the GIL
+#[pyclass(name = "Game", frozen)]
+#[derive(Clone)]
+pub struct PyGame {
+    game: OptimizedBlackJackGame,
+}
+
+// every game handed to python goes through here. Python users don't expect a bin/ directory
+// next to their script, so the file cache is off whatever the default becomes
+impl From<OptimizedBlackJackGame> for PyGame {
+    fn from(game:OptimizedBlackJackGame) -> Self {
+        Self {
+            game:game.with_cache_policy(CachePolicy::Never),
+        }
+    }
+}
+
+#[pymethods]
+impl PyGame {
+    #[staticmethod]
+    fn standard(num_decks:usize) -> Self {
+        OptimizedBlackJackGame::new_standard(num_decks).into()
+    }
+
+    // an undealt game on the ruleset's own shoe, e.g. "spanish21"
+    #[staticmethod]
+    fn with_rules(ruleset:&str, num_decks:usize) -> PyResult<Self> {
+        Ok(OptimizedBlackJackGame::new_with_ruleset(ruleset.parse::<Ruleset>()?, num_decks).into())
+    }
+
+    #[staticmethod]
+    fn from_counts(counts:[usize;10]) -> PyResult<Self> {
+        Ok(OptimizedBlackJackGame::from_counts(&counts)?.into())
+    }
+
+    #[staticmethod]
+    fn from_standard_with_removed(num_decks:usize, removed:Vec<usize>) -> PyResult<Self> {
+        Ok(OptimizedBlackJackGame::from_standard_with_removed(num_decks, &removed)?.into())
+    }
+
+    // a mid-hand position, deck_counts is the deck left after the hand and dealer cards came out
+    #[staticmethod]
+    #[pyo3(signature = (hand, dealer, deck_counts, stay=false, rules="standard"))]
+    fn from_position(hand:Vec<usize>, dealer:Vec<usize>, deck_counts:[usize;10], stay:bool, rules:&str) -> PyResult<Self> {
+        let game = OptimizedBlackJackGame::builder()
+            .hand(&hand)
+            .dealer(&dealer)
+            .stay(stay)
+            .remaining_counts(deck_counts)
+            .ruleset(rules.parse::<Ruleset>()?)
+            .build()?;
+        Ok(game.into())
+    }
+
+    fn deal(&self) -> PyResult<Self> {
+        Ok(self.game.try_deal()?.into())
+    }
+
+    fn hit(&self) -> PyResult<Self> {
+        Ok(self.game.try_hit()?.into())
+    }
+
+    fn stay(&self) -> Self {
+        self.game.stay().into()
+    }
+
+    fn double(&self) -> PyResult<Self> {
+        Ok(self.game.try_double()?.into())
+    }
+
+    // the two hands of the split, each with its second card
+    fn split(&self) -> PyResult<(Self, Self)> {
+        let (first, second) = self.game.try_split()?;
+        Ok((first.into(), second.into()))
+    }
+
+    fn dealer_hit(&self) -> PyResult<Self> {
+        Ok(self.game.try_dealer_hit()?.into())
+    }
+
+    fn new_round(&self) -> Self {
+        self.game.new_round().into()
+    }
+
+    #[getter]
+    fn hand(&self) -> Vec<usize> {
+        self.game.get_hand().clone()
+    }
+
+    #[getter]
+    fn dealer(&self) -> Vec<usize> {
+        self.game.get_dealer().clone()
+    }
+
+    #[getter]
+    fn deck_counts(&self) -> [usize;10] {
+        self.game.get_deck_counts()
+    }
+
+    #[getter]
+    fn is_stay(&self) -> bool {
+        self.game.is_stay()
+    }
+
+    #[getter]
+    fn is_doubled(&self) -> bool {
+        self.game.is_doubled()
+    }
+
+    // (at risk, free) in units of the original bet, a free bet is paid on a win but never lost
+    #[getter]
+    fn stake(&self) -> (usize, usize) {
+        let stake = self.game.get_stake();
+        (stake.at_risk, stake.free)
+    }
+
+    #[getter]
+    fn ruleset(&self) -> String {
+        self.game.get_ruleset().to_string()
+    }
+
+    #[getter]
+    fn hand_value(&self) -> usize {
+        self.game.get_hand_value()
+    }
+
+    #[getter]
+    fn dealer_value(&self) -> usize {
+        self.game.get_dealer_value()
+    }
+
+    fn unique_key(&self) -> String {
+        self.game.unique_key()
+    }
+
+    fn expected_value(&self, py:Python<'_>) -> PyResult<f64> {
+        Ok(py.allow_threads(|| self.game.try_get_expected_value())?)
+    }
+
+    fn hit_expected_value(&self, py:Python<'_>) -> PyResult<f64> {
+        Ok(py.allow_threads(|| self.game.try_get_hit_expected_value())?)
+    }
+
+    fn stay_expected_value(&self, py:Python<'_>) -> PyResult<f64> {
+        Ok(py.allow_threads(|| self.game.try_get_stay_expected_value())?)
+    }
+
+    // None when the hand can't double
+    fn double_expected_value(&self, py:Python<'_>) -> PyResult<Option<f64>> {
+        Ok(py.allow_threads(|| self.game.try_get_double_expected_value())?)
+    }
+
+    // None when the hand can't split
+    fn split_expected_value(&self, py:Python<'_>) -> PyResult<Option<f64>> {
+        Ok(py.allow_threads(|| self.game.try_get_split_expected_value())?)
+    }
+
+    fn rescue_expected_value(&self) -> Option<f64> {
+        self.game.get_rescue_expected_value()
+    }
+
+    fn action_expected_values(&self, py:Python<'_>) -> PyResult<PyActionExpectedValues> {
+        Ok(py.allow_threads(|| self.game.try_get_action_expected_values())?.into())
+    }
+
+    // (label, dealer cards) for each cell of a strategy_chart row, the upcards in UPCARD_ORDER or
+    // dealer totals when both dealer cards are face up
+    fn chart_columns(&self) -> Vec<(String, Vec<usize>)> {
+        get_chart_columns(self.game.get_ruleset()).into_iter().map(|x| (x.label, x.dealer)).collect::<Vec<(String, Vec<usize>)>>()
+    }
+
+    // exact chart for this undealt deck, every cell is a full solve
+    fn strategy_chart(&self, py:Python<'_>) -> Vec<PyChartRow> {
+        get_chart_rows(py.allow_threads(|| StrategyChart::from_game(&self.game)))
+    }
+
+    // the chart with this deck's draw probabilities held fixed, in milliseconds
+    fn infinite_deck_chart(&self) -> Vec<PyChartRow> {
+        get_chart_rows(StrategyChart::from_infinite_deck(&InfiniteDeckEngine::from_game(&self.game)))
+    }
+
+    // plays the solver's strategy trials times, returns (mean payout, standard error)
+    #[pyo3(signature = (trials, seed=None))]
+    fn simulate(&self, py:Python<'_>, trials:usize, seed:Option<u64>) -> (f64, f64) {
+        py.allow_threads(|| match seed {
+            Some(x) => simulate_expected_value_with(&self.game, trials, &mut RngSource::seeded(x)),
+            None => simulate_expected_value_with(&self.game, trials, &mut RngSource::new(rand::thread_rng())),
+        })
+    }
+
+    // (optimized ev, card engine ev, simulated ev, simulated standard error)
+    fn cross_validate(&self, py:Python<'_>, trials:usize) -> PyResult<(f64, f64, f64, f64)> {
+        let validation = py.allow_threads(|| try_cross_validate(&self.game, trials))?;
+        Ok((validation.optimized_expected_value, validation.card_expected_value, validation.simulated_expected_value, validation.simulated_standard_error))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Game({})", self.game.unique_key())
+    }
+}
+
+// the infinite deck chart for a standard shoe
+#[pyfunction]
+fn standard_chart() -> Vec<PyChartRow> {
+    get_chart_rows(StrategyChart::from_infinite_deck(&InfiniteDeckEngine::new_standard()))
+}
+
+#[pymodule]
+fn blackjack_rs(m:&Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyGame>()?;
+    m.add_class::<PyActionExpectedValues>()?;
+    m.add_function(wrap_pyfunction!(standard_chart, m)?)?;
+    m.add("UPCARD_ORDER", UPCARD_ORDER.to_vec())?;
+    m.add("BlackjackError", m.py().get_type_bound::<BlackjackError>())?;
+    Ok(())
+}
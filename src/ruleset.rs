@@ -0,0 +1,287 @@
+use game_pieces_rs::blackjack_deck::BlackjackDeck;
+
+use crate::dealer_table::{get_stay_payout, DEALER_22, DEALER_BLACKJACK, DEALER_BUST, DEALER_OUTCOMES};
+use crate::error::BlackjackError;
+use crate::opt_bj_game::{get_blackjack_value, is_soft, new_spanish_21_deck, new_standard_deck};
+
+use std::fmt;
+use std::str::FromStr;
+
+// the house rules OptimizedBlackJackGame is solved under. Every variant keeps the engine's
+// dealer: no hole card (Double Exposure's second card is dealt face up), stays on all 17s
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Ruleset {
+    // hit or stay only, blackjack pays 1.5
+    #[default]
+    Standard,
+    // no ten spot cards, player 21 always wins, 5+ card and 6-7-8 / 7-7-7 bonuses, doubling on
+    // any number of cards and double down rescue
+    Spanish21,
+    // each player plays two hands and may swap their second cards (see switch.rs), a dealer 22
+    // pushes everything but a blackjack and blackjack pays 1:1
+    Switch,
+    // free doubles on hard 9-11, free splits on every pair but tens, paid doubles on any other
+    // two cards, and a dealer 22 pushes everything but a blackjack
+    FreeBet,
+    // both dealer cards are dealt face up, the dealer wins ties except against a player blackjack
+    // and blackjack pays 1:1
+    DoubleExposure,
+}
+
+// the bet riding on a hand, in units of the original bet. A loss costs at_risk, a win pays
+// at_risk + free, so a free double risks 1 to win 2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stake {
+    pub at_risk: usize,
+    // bets the house put up, they are paid on a win but never lost
+    pub free: usize,
+    // took its one double card
+    pub doubled: bool,
+    // one of the hands from a split, a two card 21 isn't a blackjack
+    pub split: bool,
+}
+
+impl Stake {
+    pub const SINGLE:Stake = Stake { at_risk:1, free:0, doubled:false, split:false };
+    pub const DOUBLED:Stake = Stake { at_risk:2, free:0, doubled:true, split:false };
+
+    pub fn is_single(&self) -> bool {
+        *self == Stake::SINGLE
+    }
+
+    pub fn get_bet(&self) -> usize {
+        self.at_risk + self.free
+    }
+
+    // result is per unit bet: -1 for a loss, 0 for a push, 1 or more for a win
+    pub fn get_payout(&self, result:f64) -> f64 {
+        if result < 0.0 {
+            return result * self.at_risk as f64;
+        }
+        result * self.get_bet() as f64
+    }
+
+    // the double matches the whole bet, paid for by the player or by the house
+    pub fn get_doubled(&self, free:bool) -> Stake {
+        let mut stake = *self;
+        if free {
+            stake.free += self.get_bet();
+        }
+        else {
+            stake.at_risk += self.get_bet();
+        }
+        stake.doubled = true;
+        stake
+    }
+}
+
+impl Default for Stake {
+    fn default() -> Self {
+        Stake::SINGLE
+    }
+}
+
+// Spanish 21 bonuses for a 21 that wasn't doubled. The deck only tracks blackjack values, so the
+// suited and spade versions of 6-7-8 and 7-7-7 are paid as mixed
+fn get_spanish_21_payout(hand:&[usize]) -> f64 {
+    let mut sorted = hand.to_vec();
+    sorted.sort();
+    if sorted == [5, 6, 7] || sorted == [6, 6, 6] {
+        return 1.5;
+    }
+    match hand.len() {
+        0..=4 => 1.0,
+        5 => 1.5,
+        6 => 2.0,
+        _ => 3.0,
+    }
+}
+
+impl Ruleset {
+    pub fn is_standard(&self) -> bool {
+        *self == Ruleset::Standard
+    }
+
+    // the shoe these rules are dealt from
+    pub fn new_deck(&self, num_decks:usize) -> BlackjackDeck {
+        match self {
+            Ruleset::Standard => new_standard_deck(num_decks),
+            Ruleset::Spanish21 => new_spanish_21_deck(num_decks),
+            Ruleset::Switch | Ruleset::FreeBet | Ruleset::DoubleExposure => new_standard_deck(num_decks),
+        }
+    }
+
+    // how many dealer cards are dealt and known before the player's first decision
+    pub fn get_dealer_cards_dealt(&self) -> usize {
+        match self {
+            Ruleset::DoubleExposure => 2,
+            _ => 1,
+        }
+    }
+
+    // whether the hand may double now, before it has stayed
+    pub fn can_double(&self, hand:&[usize]) -> bool {
+        match self {
+            Ruleset::Standard | Ruleset::DoubleExposure => false,
+            // late doubling, on any number of cards
+            Ruleset::Spanish21 => hand.len() >= 2 && get_blackjack_value(hand) < 21,
+            // any two cards, for Switch after the switch decision
+            Ruleset::Switch | Ruleset::FreeBet => hand.len() == 2 && get_blackjack_value(hand) < 21,
+        }
+    }
+
+    // the house pays for the double
+    pub fn is_free_double(&self, hand:&[usize]) -> bool {
+        let hand_value = get_blackjack_value(hand);
+        *self == Ruleset::FreeBet && hand.len() == 2 && !is_soft(hand) && (9..=11).contains(&hand_value)
+    }
+
+    // the stake once the hand has doubled
+    pub fn get_double_stake(&self, hand:&[usize], stake:Stake) -> Stake {
+        stake.get_doubled(self.is_free_double(hand))
+    }
+
+    // whether the pair may be split, splits can't be split again
+    pub fn can_split(&self, hand:&[usize]) -> bool {
+        match self {
+            Ruleset::FreeBet => hand.len() == 2 && hand[0] == hand[1] && hand[0] != 9,
+            _ => false,
+        }
+    }
+
+    pub fn has_splits(&self) -> bool {
+        *self == Ruleset::FreeBet
+    }
+
+    // the stakes of the two hands a split makes, Free Bet puts up the second bet
+    pub fn get_split_stakes(&self, stake:Stake) -> (Stake, Stake) {
+        let first = Stake {
+            split:true,
+            ..stake
+        };
+        let second = match self {
+            Ruleset::FreeBet => Stake {
+                at_risk:0,
+                free:stake.get_bet(),
+                ..first
+            },
+            _ => first,
+        };
+        (first, second)
+    }
+
+    // a doubled hand may give up its original bet and take back the double before the dealer plays
+    pub fn has_rescue(&self) -> bool {
+        *self == Ruleset::Spanish21
+    }
+
+    // rescue hands back the double and loses the other half of what is at risk. None if the
+    // stake hasn't doubled or the rules have no rescue
+    pub fn get_rescue_payout(&self, stake:Stake) -> Option<f64> {
+        if !self.has_rescue() || !stake.doubled {
+            return None;
+        }
+        Some(-(stake.at_risk as f64) / 2.0)
+    }
+
+    // payout per original unit bet once the dealer has finished with outcome, for a hand that
+    // isn't bust
+    pub fn get_stay_payout(&self, hand:&[usize], stake:Stake, outcome:usize) -> f64 {
+        let hand_value = get_blackjack_value(hand);
+        let hand_blackjack = hand_value == 21 && hand.len() == 2 && !stake.split;
+        match self {
+            Ruleset::Standard => stake.get_payout(get_stay_payout(hand_value, hand_blackjack, outcome)),
+            Ruleset::Spanish21 => {
+                // a player blackjack beats a dealer blackjack
+                if hand_blackjack {
+                    return stake.get_payout(1.5);
+                }
+                if outcome == DEALER_BLACKJACK {
+                    return stake.get_payout(-1.0);
+                }
+                // player 21 always wins
+                if hand_value == 21 {
+                    if stake.doubled {
+                        return stake.get_payout(1.0);
+                    }
+                    return stake.get_payout(get_spanish_21_payout(hand));
+                }
+                if outcome == DEALER_BUST || outcome == DEALER_22 {
+                    return stake.get_payout(1.0);
+                }
+                stake.get_payout(get_stay_payout(hand_value, false, outcome))
+            }
+            Ruleset::Switch | Ruleset::FreeBet => {
+                if outcome == DEALER_BLACKJACK {
+                    // you can push though
+                    if hand_blackjack {
+                        return 0.0;
+                    }
+                    return stake.get_payout(-1.0);
+                }
+                if hand_blackjack {
+                    return stake.get_payout(if *self == Ruleset::Switch { 1.0 } else { 1.5 });
+                }
+                if outcome == DEALER_22 {
+                    return 0.0;
+                }
+                stake.get_payout(get_stay_payout(hand_value, false, outcome))
+            }
+            Ruleset::DoubleExposure => {
+                // a player blackjack wins every tie, at even money
+                if hand_blackjack {
+                    return stake.get_payout(1.0);
+                }
+                if outcome == DEALER_BLACKJACK {
+                    return stake.get_payout(-1.0);
+                }
+                let result = get_stay_payout(hand_value, false, outcome);
+                // ties lose
+                if result == 0.0 {
+                    return stake.get_payout(-1.0);
+                }
+                stake.get_payout(result)
+            }
+        }
+    }
+
+    pub fn get_stay_expected_value(&self, hand:&[usize], stake:Stake, outcome_probs:&[f64;DEALER_OUTCOMES]) -> f64 {
+        let mut expected_value = 0.0;
+        for (outcome, prob) in outcome_probs.iter().enumerate() {
+            if *prob > 0.0 {
+                expected_value += prob * self.get_stay_payout(hand, stake, outcome);
+            }
+        }
+        expected_value
+    }
+}
+
+impl fmt::Display for Ruleset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ruleset::Standard => write!(f, "standard"),
+            Ruleset::Spanish21 => write!(f, "spanish21"),
+            Ruleset::Switch => write!(f, "switch"),
+            Ruleset::FreeBet => write!(f, "free_bet"),
+            Ruleset::DoubleExposure => write!(f, "double_exposure"),
+        }
+    }
+}
+
+impl FromStr for Ruleset {
+    type Err = BlackjackError;
+
+    fn from_str(name:&str) -> Result<Self, BlackjackError> {
+        match name {
+            "standard" => Ok(Ruleset::Standard),
+            "spanish21" => Ok(Ruleset::Spanish21),
+            "switch" => Ok(Ruleset::Switch),
+            "free_bet" => Ok(Ruleset::FreeBet),
+            "double_exposure" => Ok(Ruleset::DoubleExposure),
+            _ => Err(BlackjackError::UnknownRuleset(name.to_string())),
+        }
+    }
+}
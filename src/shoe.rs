@@ -0,0 +1,169 @@
+use game_pieces_rs::blackjack_deck::BlackjackDeck;
+
+use crate::card_source::{draw_with, CardSource, RngSource};
+use crate::opt_bj_game::new_standard_deck;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShuffleMode {
+    // shuffle at the end of the round in which the cut card comes out, cut_card cards in
+    CutCard { cut_card: usize, burn_cards: usize },
+    // shuffle after a fixed number of rounds, no matter how many cards they used
+    FixedRounds { rounds: usize, burn_cards: usize },
+    // continuous shuffling machine, every discard goes straight back into the shoe
+    Continuous,
+}
+
+pub struct Shoe {
+    num_decks: usize,
+    mode: ShuffleMode,
+    deck: BlackjackDeck,
+    // cards dealt since the last shuffle, including burn cards
+    dealt: usize,
+    // face up cards since the last shuffle, what a counter gets to see
+    seen: Vec<usize>,
+    round_cards: Vec<usize>,
+    rounds_this_shoe: usize,
+    shuffles: usize,
+    // None draws with the deck's own rng
+    source: Option<Box<dyn CardSource + Send>>,
+}
+
+impl Shoe {
+    pub fn new(num_decks:usize, mode:ShuffleMode) -> Self {
+        let mut shoe = Self {
+            num_decks:num_decks,
+            mode:mode,
+            deck:new_standard_deck(num_decks),
+            dealt:0,
+            seen:Vec::<usize>::new(),
+            round_cards:Vec::<usize>::new(),
+            rounds_this_shoe:0,
+            shuffles:0,
+            source:None,
+        };
+        shoe.shuffle();
+        shoe
+    }
+
+    // penetration is the fraction of the shoe in front of the cut card
+    pub fn new_cut_card(num_decks:usize, penetration:f64, burn_cards:usize) -> Self {
+        if !(penetration > 0.0 && penetration <= 1.0) {
+            panic!("penetration must be in (0, 1], got {}", penetration)
+        }
+        let cut_card = (penetration * (num_decks * 52) as f64).round() as usize;
+        Self::new(num_decks, ShuffleMode::CutCard { cut_card:cut_card, burn_cards:burn_cards })
+    }
+
+    pub fn new_continuous(num_decks:usize) -> Self {
+        Self::new(num_decks, ShuffleMode::Continuous)
+    }
+
+    // every card after this, burn cards included, comes from source. The shoe is shuffled again
+    // so the first shoe uses it too. A stacked source panics the draw that runs it dry
+    pub fn with_card_source(mut self, source:Box<dyn CardSource + Send>) -> Self {
+        self.source = Some(source);
+        self.shuffles = 0;
+        self.shuffle();
+        self
+    }
+
+    pub fn with_seed(self, seed:u64) -> Self {
+        self.with_card_source(Box::new(RngSource::seeded(seed)))
+    }
+
+    fn draw_card(&mut self) -> usize {
+        let (card, updated_deck) = match &mut self.source {
+            Some(source) => draw_with(&self.deck, source.as_mut()).unwrap_or_else(|e| panic!("{}", e)),
+            None => self.deck.draw(),
+        };
+        self.deck = updated_deck;
+        card
+    }
+
+    pub fn get_num_decks(&self) -> usize {
+        self.num_decks
+    }
+
+    pub fn get_mode(&self) -> ShuffleMode {
+        self.mode
+    }
+
+    pub fn get_deck(&self) -> &BlackjackDeck {
+        &self.deck
+    }
+
+    pub fn get_cards_remaining(&self) -> usize {
+        self.deck.count
+    }
+
+    pub fn get_dealt(&self) -> usize {
+        self.dealt
+    }
+
+    pub fn get_seen(&self) -> &Vec<usize> {
+        &self.seen
+    }
+
+    pub fn get_shuffles(&self) -> usize {
+        self.shuffles
+    }
+
+    pub fn get_penetration(&self) -> f64 {
+        self.dealt as f64 / (self.num_decks * 52) as f64
+    }
+
+    pub fn needs_shuffle(&self) -> bool {
+        match self.mode {
+            ShuffleMode::CutCard { cut_card, .. } => self.dealt >= cut_card,
+            ShuffleMode::FixedRounds { rounds, .. } => self.rounds_this_shoe >= rounds,
+            ShuffleMode::Continuous => true,
+        }
+    }
+
+    fn shuffle(&mut self) {
+        self.deck = new_standard_deck(self.num_decks);
+        self.dealt = 0;
+        self.seen.clear();
+        self.rounds_this_shoe = 0;
+        self.shuffles += 1;
+        let burn_cards = match self.mode {
+            ShuffleMode::CutCard { burn_cards, .. } => burn_cards,
+            ShuffleMode::FixedRounds { burn_cards, .. } => burn_cards,
+            ShuffleMode::Continuous => 0,
+        };
+        for _i in 0..burn_cards {
+            self.draw_card();
+            self.dealt += 1;
+        }
+    }
+
+    // returns true if the shoe was shuffled before this round
+    pub fn start_round(&mut self) -> bool {
+        self.round_cards.clear();
+        let shuffled = self.needs_shuffle();
+        if shuffled {
+            self.shuffle();
+        }
+        self.rounds_this_shoe += 1;
+        shuffled
+    }
+
+    pub fn draw(&mut self) -> usize {
+        if self.deck.count == 0 {
+            // the shoe ran dry mid round, reshuffle everything that is not on the table
+            let mut deck = new_standard_deck(self.num_decks);
+            for card in self.round_cards.iter() {
+                (_, deck) = deck.draw_blackjack_value_index(*card);
+            }
+            self.deck = deck;
+            self.dealt = self.round_cards.len();
+            self.seen = self.round_cards.clone();
+            self.shuffles += 1;
+        }
+        let card = self.draw_card();
+        self.dealt += 1;
+        self.seen.push(card);
+        self.round_cards.push(card);
+        card
+    }
+}
@@ -0,0 +1,248 @@
+use crate::action::ActionExpectedValues;
+use crate::error::BlackjackError;
+use crate::game::{get_card_indices, BlackJackGame};
+use crate::opt_bj_game::{get_blackjack_value, new_deck_from_counts, OptimizedBlackJackGame};
+use crate::ruleset::{Ruleset, Stake};
+
+// what both engines have in common. Cards are exposed as blackjack value indices
+// (0 = ace, 9 = ten value) so that positions and rules can be compared across engines
+pub trait BlackjackState: Sized {
+    fn get_hand_indices(&self) -> Vec<usize>;
+    fn get_dealer_indices(&self) -> Vec<usize>;
+    fn is_stay(&self) -> bool;
+    fn get_deck_counts(&self) -> [usize;10];
+
+    fn try_deal(&self) -> Result<Self, BlackjackError>;
+    fn try_hit(&self) -> Result<Self, BlackjackError>;
+    fn try_dealer_hit(&self) -> Result<Self, BlackjackError>;
+    fn stay(&self) -> Self;
+
+    fn get_hit_expected_value(&self) -> f64;
+    fn get_stay_expected_value(&self) -> f64;
+    fn get_expected_value(&self) -> f64;
+    // every action the engine's rules allow, so the best action matches the engine's own
+    fn get_action_expected_values(&self) -> ActionExpectedValues;
+
+    fn get_hand_value(&self) -> usize {
+        get_blackjack_value(&self.get_hand_indices())
+    }
+
+    fn get_dealer_value(&self) -> usize {
+        get_blackjack_value(&self.get_dealer_indices())
+    }
+
+    fn is_hand_bust(&self) -> bool {
+        self.get_hand_value() > 21
+    }
+
+    fn is_dealer_bust(&self) -> bool {
+        self.get_dealer_value() > 21
+    }
+
+    fn is_hand_blackjack(&self) -> bool {
+        self.get_hand_value() == 21 && self.get_hand_indices().len() == 2
+    }
+
+    fn is_dealer_blackjack(&self) -> bool {
+        self.get_dealer_value() == 21 && self.get_dealer_indices().len() == 2
+    }
+}
+
+impl BlackjackState for OptimizedBlackJackGame {
+    fn get_hand_indices(&self) -> Vec<usize> {
+        self.get_hand().clone()
+    }
+
+    fn get_dealer_indices(&self) -> Vec<usize> {
+        self.get_dealer().clone()
+    }
+
+    fn is_stay(&self) -> bool {
+        OptimizedBlackJackGame::is_stay(self)
+    }
+
+    fn get_deck_counts(&self) -> [usize;10] {
+        OptimizedBlackJackGame::get_deck_counts(self)
+    }
+
+    fn try_deal(&self) -> Result<Self, BlackjackError> {
+        OptimizedBlackJackGame::try_deal(self)
+    }
+
+    fn try_hit(&self) -> Result<Self, BlackjackError> {
+        OptimizedBlackJackGame::try_hit(self)
+    }
+
+    fn try_dealer_hit(&self) -> Result<Self, BlackjackError> {
+        OptimizedBlackJackGame::try_dealer_hit(self)
+    }
+
+    fn stay(&self) -> Self {
+        OptimizedBlackJackGame::stay(self)
+    }
+
+    fn get_hit_expected_value(&self) -> f64 {
+        OptimizedBlackJackGame::get_hit_expected_value(self)
+    }
+
+    fn get_stay_expected_value(&self) -> f64 {
+        OptimizedBlackJackGame::get_stay_expected_value(self)
+    }
+
+    fn get_expected_value(&self) -> f64 {
+        OptimizedBlackJackGame::get_expected_value(self)
+    }
+
+    fn get_action_expected_values(&self) -> ActionExpectedValues {
+        OptimizedBlackJackGame::get_action_expected_values(self)
+    }
+}
+
+impl BlackjackState for BlackJackGame {
+    fn get_hand_indices(&self) -> Vec<usize> {
+        get_card_indices(self.get_hand())
+    }
+
+    fn get_dealer_indices(&self) -> Vec<usize> {
+        get_card_indices(self.get_dealer())
+    }
+
+    fn is_stay(&self) -> bool {
+        BlackJackGame::is_stay(self)
+    }
+
+    fn get_deck_counts(&self) -> [usize;10] {
+        BlackJackGame::get_deck_counts(self)
+    }
+
+    fn try_deal(&self) -> Result<Self, BlackjackError> {
+        BlackJackGame::try_deal(self)
+    }
+
+    fn try_hit(&self) -> Result<Self, BlackjackError> {
+        BlackJackGame::try_hit(self)
+    }
+
+    fn try_dealer_hit(&self) -> Result<Self, BlackjackError> {
+        BlackJackGame::try_dealer_hit(self)
+    }
+
+    fn stay(&self) -> Self {
+        BlackJackGame::stay(self)
+    }
+
+    fn get_hit_expected_value(&self) -> f64 {
+        BlackJackGame::get_hit_expected_value(self)
+    }
+
+    fn get_stay_expected_value(&self) -> f64 {
+        BlackJackGame::get_stay_expected_value(self)
+    }
+
+    fn get_expected_value(&self) -> f64 {
+        BlackJackGame::get_expected_value(self)
+    }
+
+    // the card engine only plays standard hit or stay
+    fn get_action_expected_values(&self) -> ActionExpectedValues {
+        ActionExpectedValues {
+            hit:BlackJackGame::get_hit_expected_value(self),
+            stay:BlackJackGame::get_stay_expected_value(self),
+            double:None,
+            split:None,
+        }
+    }
+}
+
+// the JSON schema for a position from either engine. Suits, face cards and the cache policy are
+// not part of it, a BlackJackGame comes back with representative cards and the default policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameState {
+    pub hand: Vec<usize>,
+    pub dealer: Vec<usize>,
+    pub stay: bool,
+    pub deck_counts: [usize;10],
+    // left out for standard rules and undoubled hands, so older states still read
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Ruleset::is_standard"))]
+    pub ruleset: Ruleset,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "std::ops::Not::not"))]
+    pub doubled: bool,
+    // only for the hands of a split, every other stake follows from the ruleset and doubled
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub stake: Option<Stake>,
+}
+
+impl GameState {
+    pub fn from_state<T: BlackjackState>(state:&T) -> Self {
+        Self {
+            hand:state.get_hand_indices(),
+            dealer:state.get_dealer_indices(),
+            stay:state.is_stay(),
+            deck_counts:state.get_deck_counts(),
+            ruleset:Ruleset::Standard,
+            doubled:false,
+            stake:None,
+        }
+    }
+}
+
+impl From<&OptimizedBlackJackGame> for GameState {
+    fn from(game:&OptimizedBlackJackGame) -> Self {
+        Self {
+            ruleset:game.get_ruleset(),
+            doubled:game.is_doubled(),
+            stake:Some(game.get_stake()).filter(|x| x.split),
+            ..Self::from_state(game)
+        }
+    }
+}
+
+impl From<OptimizedBlackJackGame> for GameState {
+    fn from(game:OptimizedBlackJackGame) -> Self {
+        Self::from(&game)
+    }
+}
+
+impl From<&BlackJackGame> for GameState {
+    fn from(game:&BlackJackGame) -> Self {
+        Self::from_state(game)
+    }
+}
+
+impl From<BlackJackGame> for GameState {
+    fn from(game:BlackJackGame) -> Self {
+        Self::from_state(&game)
+    }
+}
+
+impl TryFrom<GameState> for OptimizedBlackJackGame {
+    type Error = BlackjackError;
+
+    fn try_from(state:GameState) -> Result<Self, BlackjackError> {
+        for card in state.hand.iter().chain(state.dealer.iter()) {
+            if *card >= 10 {
+                return Err(BlackjackError::InvalidCardIndex(*card));
+            }
+        }
+        let game = OptimizedBlackJackGame::from_parts(state.hand, state.dealer, state.stay, new_deck_from_counts(&state.deck_counts))
+            .with_ruleset(state.ruleset)
+            .with_doubled(state.doubled);
+        Ok(match state.stake {
+            Some(x) => game.with_stake(x),
+            None => game,
+        })
+    }
+}
+
+impl TryFrom<GameState> for BlackJackGame {
+    type Error = BlackjackError;
+
+    // the card engine plays standard hit or stay, reading anything else into it would value a different game
+    fn try_from(state:GameState) -> Result<Self, BlackjackError> {
+        if !state.ruleset.is_standard() || state.doubled || state.stake.is_some() {
+            return Err(BlackjackError::UnsupportedRuleset(format!("the card engine only plays standard undoubled hands, not {} with doubled {} and stake {:?}", state.ruleset, state.doubled, state.stake)));
+        }
+        BlackJackGame::try_from(&OptimizedBlackJackGame::try_from(state)?)
+    }
+}
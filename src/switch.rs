@@ -0,0 +1,120 @@
+use crate::action::ActionExpectedValues;
+use crate::error::BlackjackError;
+use crate::opt_bj_game::OptimizedBlackJackGame;
+use crate::ruleset::Ruleset;
+
+// the two hands a Blackjack Switch player holds, dealt against the same upcard. Cards are
+// blackjack value indices, remaining_counts is the shoe left after both hands and the upcard
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwitchHands {
+    pub first: Vec<usize>,
+    pub second: Vec<usize>,
+    pub upcard: usize,
+    pub remaining_counts: [usize;10],
+}
+
+// joint ev of both hands, per unit bet on each, with and without swapping the second cards.
+// Approximate: each hand is solved as if the other weren't there, see
+// get_approximate_switch_expected_values
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwitchExpectedValues {
+    pub keep: f64,
+    pub switch: f64,
+}
+
+impl SwitchExpectedValues {
+    // ties keep the hands as dealt
+    pub fn should_switch(&self) -> bool {
+        self.switch > self.keep
+    }
+
+    pub fn get_best_expected_value(&self) -> f64 {
+        f64::max(self.keep, self.switch)
+    }
+}
+
+impl SwitchHands {
+    // deals two hands and the upcard off an undealt game, in casino order: a card to each hand,
+    // the upcard, then the second card to each hand
+    pub fn try_deal(game:&OptimizedBlackJackGame) -> Result<Self, BlackjackError> {
+        if !game.get_hand().is_empty() || !game.get_dealer().is_empty() {
+            return Err(BlackjackError::AlreadyDealt);
+        }
+        if game.get_deck_count() < 5 {
+            return Err(BlackjackError::NotEnoughCards { needed:5, available:game.get_deck_count() });
+        }
+        let mut cards = Vec::<usize>::new();
+        let mut deck = game.get_deck().clone();
+        for _i in 0..5 {
+            let (card, drawn_deck) = deck.draw();
+            cards.push(card);
+            deck = drawn_deck;
+        }
+        let mut remaining_counts = [0usize;10];
+        remaining_counts.clone_from_slice(&deck.count_by_blackjack_value);
+        Ok(Self {
+            first:vec![cards[0], cards[3]],
+            second:vec![cards[1], cards[4]],
+            upcard:cards[2],
+            remaining_counts:remaining_counts,
+        })
+    }
+
+    pub fn deal(game:&OptimizedBlackJackGame) -> Self {
+        Self::try_deal(game).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // the hands with their second cards swapped
+    pub fn switched(&self) -> Self {
+        let mut first = self.first.clone();
+        let mut second = self.second.clone();
+        if first.len() > 1 && second.len() > 1 {
+            std::mem::swap(&mut first[1], &mut second[1]);
+        }
+        Self {
+            first:first,
+            second:second,
+            upcard:self.upcard,
+            remaining_counts:self.remaining_counts,
+        }
+    }
+
+    // each hand as a Switch position on the shoe left after both hands came out. The hands are
+    // played one at a time, so the cards the other hand draws aren't known to either position
+    pub fn try_get_positions(&self) -> Result<(OptimizedBlackJackGame, OptimizedBlackJackGame), BlackjackError> {
+        let get_position = |hand:&[usize]| OptimizedBlackJackGame::builder()
+            .hand(hand)
+            .dealer(&[self.upcard])
+            .remaining_counts(self.remaining_counts)
+            .ruleset(Ruleset::Switch)
+            .build();
+        Ok((get_position(&self.first)?, get_position(&self.second)?))
+    }
+
+    fn try_get_joint_expected_value(&self) -> Result<f64, BlackjackError> {
+        let (first, second) = self.try_get_positions()?;
+        Ok(first.try_get_expected_value()? + second.try_get_expected_value()?)
+    }
+
+    // the joint ev adds up each hand solved alone on the same shoe. The cards one hand draws change
+    // the shoe for the other, which neither position sees, so this is an approximation and
+    // should_switch can be wrong when the two are close
+    pub fn try_get_approximate_switch_expected_values(&self) -> Result<SwitchExpectedValues, BlackjackError> {
+        Ok(SwitchExpectedValues {
+            keep:self.try_get_joint_expected_value()?,
+            switch:self.switched().try_get_joint_expected_value()?,
+        })
+    }
+
+    pub fn get_approximate_switch_expected_values(&self) -> SwitchExpectedValues {
+        self.try_get_approximate_switch_expected_values().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // hit, stay or double for both hands, after the switch decision
+    pub fn try_get_action_expected_values(&self) -> Result<(ActionExpectedValues, ActionExpectedValues), BlackjackError> {
+        let (first, second) = self.try_get_positions()?;
+        Ok((first.try_get_action_expected_values()?, second.try_get_action_expected_values()?))
+    }
+}
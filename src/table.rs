@@ -0,0 +1,283 @@
+use game_pieces_rs::blackjack_deck::BlackjackDeck;
+
+use crate::action::Action;
+use crate::counting::CountingSystem;
+use crate::dealer_table::get_dealer_outcome;
+use crate::opt_bj_game::{get_blackjack_value, is_soft, OptimizedBlackJackGame};
+use crate::parallel_solver::ParallelSolver;
+use crate::ruleset::{Ruleset, Stake};
+use crate::shoe::Shoe;
+
+pub const MAX_SEATS:usize = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    // hit until the hand is worth at least this much
+    StayOn(usize),
+    // hit/stay basic strategy for a dealer that stays on all 17s
+    Basic,
+    // ask the exact solver at every decision. A table runs these through one ParallelSolver,
+    // which is still only practical on 1 or 2 deck shoes
+    Optimal,
+}
+
+impl Strategy {
+    pub fn get_action(&self, hand:&[usize], dealer:&[usize], deck:&BlackjackDeck) -> Action {
+        let value = get_blackjack_value(hand);
+        match self {
+            Strategy::StayOn(stay_value) => {
+                if value >= *stay_value {
+                    return Action::Stay;
+                }
+                Action::Hit
+            }
+            Strategy::Basic => get_basic_action(value, is_soft(hand), dealer[0]),
+            Strategy::Optimal => {
+                let game = OptimizedBlackJackGame::from_parts(hand.to_vec(), dealer.to_vec(), false, deck.clone());
+                game.get_action_expected_values().best_action()
+            }
+        }
+    }
+}
+
+fn get_basic_action(value:usize, soft:bool, upcard:usize) -> Action {
+    // an ace up counts as 11
+    let upcard_value = if upcard == 0 { 11 } else { upcard + 1 };
+    let stay = if soft {
+        value >= 19 || (value == 18 && upcard_value <= 8)
+    }
+    else {
+        value >= 17 || (value >= 13 && upcard_value <= 6) || (value == 12 && (4..=6).contains(&upcard_value))
+    };
+    if stay {
+        return Action::Stay;
+    }
+    Action::Hit
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BetPolicy {
+    Flat(f64),
+    // one unit per point of betting count, at least one unit and at most max_units
+    CountRamp { system: CountingSystem, unit: f64, max_units: f64 },
+}
+
+impl BetPolicy {
+    pub fn get_bet(&self, seen:&[usize], num_decks:usize, cards_remaining:usize) -> f64 {
+        match self {
+            BetPolicy::Flat(bet) => *bet,
+            BetPolicy::CountRamp { system, unit, max_units } => {
+                let running_count = system.get_initial_running_count(num_decks) + system.get_running_count(seen);
+                let count = system.get_betting_count(running_count, cards_remaining);
+                unit * f64::min(f64::max(count.floor(), 1.0), *max_units)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Seat {
+    pub strategy: Strategy,
+    pub bet_policy: BetPolicy,
+}
+
+impl Seat {
+    pub fn new(strategy:Strategy, bet_policy:BetPolicy) -> Self {
+        Self {
+            strategy:strategy,
+            bet_policy:bet_policy,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SeatResult {
+    pub hands: usize,
+    pub total_bet: f64,
+    pub net_result: f64,
+    pub squared_result: f64,
+}
+
+impl SeatResult {
+    pub fn get_expected_value_per_hand(&self) -> f64 {
+        if self.hands == 0 {
+            return 0.0;
+        }
+        self.net_result / self.hands as f64
+    }
+
+    // standard error of get_expected_value_per_hand
+    pub fn get_standard_error(&self) -> f64 {
+        if self.hands < 2 {
+            return 0.0;
+        }
+        let hands = self.hands as f64;
+        let mean = self.net_result / hands;
+        let variance = (self.squared_result / hands - mean * mean) * hands / (hands - 1.0);
+        f64::sqrt(f64::max(variance, 0.0) / hands)
+    }
+
+    pub fn get_return_per_bet(&self) -> f64 {
+        if self.total_bet == 0.0 {
+            return 0.0;
+        }
+        self.net_result / self.total_bet
+    }
+}
+
+// seats are numbered from the dealer's left (first base) and are dealt and played in that order
+pub struct Table {
+    seats: Vec<Seat>,
+    shoe: Shoe,
+    // only made when a seat plays Strategy::Optimal, its memo is cleared at each shuffle
+    solver: Option<ParallelSolver>,
+    seat_results: Vec<SeatResult>,
+    rounds: usize,
+    rounds_this_shoe: usize,
+    completed_shoes: usize,
+    completed_shoe_rounds: usize,
+}
+
+impl Table {
+    // penetration is the fraction of the shoe dealt before it is reshuffled
+    pub fn new(num_decks:usize, penetration:f64, seats:Vec<Seat>) -> Self {
+        Self::with_shoe(Shoe::new_cut_card(num_decks, penetration, 0), seats)
+    }
+
+    pub fn with_shoe(shoe:Shoe, seats:Vec<Seat>) -> Self {
+        if seats.is_empty() || seats.len() > MAX_SEATS {
+            panic!("a table needs between 1 and {} seats, got {}", MAX_SEATS, seats.len())
+        }
+        let seat_results = vec![SeatResult::default(); seats.len()];
+        let solver = if seats.iter().any(|x| x.strategy == Strategy::Optimal) {
+            Some(ParallelSolver::new(0).unwrap_or_else(|e| panic!("{}", e)))
+        }
+        else {
+            None
+        };
+        Self {
+            seats:seats,
+            shoe:shoe,
+            solver:solver,
+            seat_results:seat_results,
+            rounds:0,
+            rounds_this_shoe:0,
+            completed_shoes:0,
+            completed_shoe_rounds:0,
+        }
+    }
+
+    pub fn get_seats(&self) -> &Vec<Seat> {
+        &self.seats
+    }
+
+    pub fn get_shoe(&self) -> &Shoe {
+        &self.shoe
+    }
+
+    pub fn get_seat_results(&self) -> &Vec<SeatResult> {
+        &self.seat_results
+    }
+
+    pub fn get_rounds(&self) -> usize {
+        self.rounds
+    }
+
+    pub fn get_rounds_per_shoe(&self) -> f64 {
+        if self.completed_shoes == 0 {
+            return self.rounds_this_shoe as f64;
+        }
+        self.completed_shoe_rounds as f64 / self.completed_shoes as f64
+    }
+
+    pub fn play_round(&mut self) {
+        let num_decks = self.shoe.get_num_decks();
+        if self.shoe.start_round() {
+            if let Some(solver) = &self.solver {
+                solver.clear_memo();
+            }
+            if self.rounds_this_shoe > 0 {
+                self.completed_shoes += 1;
+                self.completed_shoe_rounds += self.rounds_this_shoe;
+                self.rounds_this_shoe = 0;
+            }
+        }
+        let num_seats = self.seats.len();
+        let bets = self.seats.iter().map(|x| x.bet_policy.get_bet(self.shoe.get_seen(), num_decks, self.shoe.get_cards_remaining())).collect::<Vec<f64>>();
+
+        // casino order: one card to each seat, the dealer's upcard, then a second card to each seat
+        let mut hands = vec![Vec::<usize>::new(); num_seats];
+        for hand in hands.iter_mut() {
+            hand.push(self.shoe.draw());
+        }
+        let mut dealer = vec![self.shoe.draw()];
+        for hand in hands.iter_mut() {
+            hand.push(self.shoe.draw());
+        }
+
+        for i in 0..num_seats {
+            while get_blackjack_value(&hands[i]) < 21 {
+                let action = match (&self.solver, self.seats[i].strategy) {
+                    (Some(solver), Strategy::Optimal) => {
+                        let game = OptimizedBlackJackGame::from_parts(hands[i].clone(), dealer.clone(), false, self.shoe.get_deck().clone());
+                        solver.get_action_expected_values(&game).best_action()
+                    }
+                    (_, strategy) => strategy.get_action(&hands[i], &dealer, self.shoe.get_deck()),
+                };
+                match action {
+                    Action::Hit => {
+                        let card = self.shoe.draw();
+                        hands[i].push(card);
+                    }
+                    // table strategies only play standard hit or stay
+                    Action::Stay | Action::Double | Action::Split => break,
+                }
+            }
+        }
+
+        // dealer stays on >=17, and does not draw at all if every seat is bust
+        if hands.iter().any(|x| get_blackjack_value(x) <= 21) {
+            while get_blackjack_value(&dealer) < 17 {
+                dealer.push(self.shoe.draw());
+            }
+        }
+
+        // the dealer only stops short of 17 when every seat is bust
+        let outcome = get_dealer_outcome(&dealer);
+        for i in 0..num_seats {
+            let payout = match outcome {
+                Some(outcome) if get_blackjack_value(&hands[i]) <= 21 => Ruleset::Standard.get_stay_payout(&hands[i], Stake::SINGLE, outcome),
+                _ => -1.0,
+            };
+            self.seat_results[i].hands += 1;
+            self.seat_results[i].total_bet += bets[i];
+            self.seat_results[i].net_result += bets[i] * payout;
+            self.seat_results[i].squared_result += (bets[i] * payout).powi(2);
+        }
+        self.rounds += 1;
+        self.rounds_this_shoe += 1;
+    }
+
+    pub fn simulate(&mut self, rounds:usize) {
+        for _i in 0..rounds {
+            self.play_round();
+        }
+    }
+
+    pub fn print_contents(&self) {
+        println!("Decks: {}, Shuffle: {:?}, Seats: {}", self.shoe.get_num_decks(), self.shoe.get_mode(), self.seats.len());
+        println!("Rounds: {}, Rounds per shoe: {:.2}", self.rounds, self.get_rounds_per_shoe());
+        for (i, (seat, result)) in self.seats.iter().zip(self.seat_results.iter()).enumerate() {
+            println!(
+                "Seat {}: {:?}, {:?}, Hands: {}, Net: {:+.2}, EV per hand: {:+.5}, Return per unit bet: {:+.5}",
+                i + 1,
+                seat.strategy,
+                seat.bet_policy,
+                result.hands,
+                result.net_result,
+                result.get_expected_value_per_hand(),
+                result.get_return_per_bet(),
+            );
+        }
+    }
+}
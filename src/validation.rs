@@ -0,0 +1,183 @@
+use crate::action::Action;
+use crate::card_source::{CardSource, RngSource};
+use crate::error::BlackjackError;
+use crate::game::BlackJackGame;
+use crate::opt_bj_game::{new_deck_from_counts, OptimizedBlackJackGame};
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+// the same position solved by both exact engines and by monte carlo play of the solver's own strategy
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CrossValidation {
+    pub optimized_expected_value: f64,
+    pub optimized_seconds: f64,
+    pub card_expected_value: f64,
+    pub card_seconds: f64,
+    pub simulated_expected_value: f64,
+    pub simulated_standard_error: f64,
+    pub simulated_seconds: f64,
+    pub trials: usize,
+}
+
+impl CrossValidation {
+    pub fn get_engine_difference(&self) -> f64 {
+        (self.optimized_expected_value - self.card_expected_value).abs()
+    }
+
+    // how many standard errors the simulation is away from the exact answer
+    pub fn get_simulation_error(&self) -> f64 {
+        let difference = (self.optimized_expected_value - self.simulated_expected_value).abs();
+        if self.simulated_standard_error == 0.0 {
+            if difference < 1e-9 {
+                return 0.0;
+            }
+            return f64::INFINITY;
+        }
+        difference / self.simulated_standard_error
+    }
+
+    // the exact engines must agree to rounding, the simulation to within tolerance standard errors
+    pub fn has_discrepancy(&self, tolerance:f64) -> bool {
+        self.get_engine_difference() > 1e-9 || self.get_simulation_error() > tolerance
+    }
+
+    pub fn print_contents(&self) {
+        println!("Optimized EV: {:+.6} ({:.3}s)", self.optimized_expected_value, self.optimized_seconds);
+        println!("Card EV: {:+.6} ({:.3}s)", self.card_expected_value, self.card_seconds);
+        println!("Simulated EV: {:+.6} +/- {:.6} over {} trials ({:.3}s)", self.simulated_expected_value, self.simulated_standard_error, self.trials, self.simulated_seconds);
+        println!("Engine difference: {:.3e}, Simulation error: {:.2} standard errors", self.get_engine_difference(), self.get_simulation_error());
+    }
+}
+
+// an undealt game on a deck built from counts per blackjack value index
+pub fn new_small_deck_game(counts:&[usize;10]) -> OptimizedBlackJackGame {
+    OptimizedBlackJackGame::new_from_deck(new_deck_from_counts(counts))
+}
+
+// one suit, 13 cards
+pub fn new_single_suit_game() -> OptimizedBlackJackGame {
+    new_small_deck_game(&[1, 1, 1, 1, 1, 1, 1, 1, 1, 4])
+}
+
+fn get_best_action(game:&OptimizedBlackJackGame, decisions:&mut HashMap<String, Action>) -> Action {
+    let key = game.unique_key();
+    if let Some(action) = decisions.get(&key) {
+        return *action;
+    }
+    let action = game.get_action_expected_values().best_action();
+    decisions.insert(key, action);
+    action
+}
+
+// plays one round the way the solver models it: an undealt game gets the dealer's face up cards
+// and then one player card before the first decision, exactly like get_deck_expected_value
+fn simulate_round<S: CardSource>(game:&OptimizedBlackJackGame, decisions:&mut HashMap<String, Action>, source:&mut S) -> f64 {
+    let mut game = game.clone();
+    if game.get_hand().is_empty() && game.get_dealer().is_empty() {
+        while game.get_dealer().len() < game.get_ruleset().get_dealer_cards_dealt() {
+            game = game.dealer_hit_with(source);
+        }
+        game = game.hit_with(source);
+    }
+    // the solver values drawing from an empty deck at 0, so the simulation has to as well
+    if !game.is_stay() {
+        while !game.is_hand_bust() {
+            match get_best_action(&game, decisions) {
+                Action::Hit if game.get_deck_count() == 0 => return 0.0,
+                Action::Hit => game = game.hit_with(source),
+                Action::Stay => break,
+                Action::Double if game.get_deck_count() == 0 => return 0.0,
+                Action::Double => {
+                    game = game.double_with(source);
+                    // rescue when it's worth more than playing the double out
+                    if let Some(rescue_ev) = game.get_rescue_expected_value() {
+                        if rescue_ev > game.get_stay_expected_value() {
+                            return rescue_ev;
+                        }
+                    }
+                    break;
+                }
+                Action::Split if game.get_deck_count() < 2 => return 0.0,
+                // the solver values each hand of a split on its own, so each plays out its own round
+                Action::Split => {
+                    let (first, second) = game.split_with(source);
+                    return simulate_round(&first, decisions, source) + simulate_round(&second, decisions, source);
+                }
+            }
+        }
+    }
+    let mut settled = game.stay();
+    if !settled.is_hand_bust() {
+        // dealer stays on >=17
+        while settled.get_dealer_value() < 17 {
+            if settled.get_deck_count() == 0 {
+                return 0.0;
+            }
+            settled = settled.dealer_hit_with(source).stay();
+        }
+    }
+    // the position is fully resolved, so its ev is the payout
+    settled.get_expected_value()
+}
+
+pub fn simulate_expected_value(game:&OptimizedBlackJackGame, trials:usize) -> (f64, f64) {
+    simulate_expected_value_with(game, trials, &mut RngSource::new(rand::thread_rng()))
+}
+
+// same as simulate_expected_value with the cards from source, a seeded source replays exactly
+pub fn simulate_expected_value_with<S: CardSource>(game:&OptimizedBlackJackGame, trials:usize, source:&mut S) -> (f64, f64) {
+    let mut decisions = HashMap::<String, Action>::new();
+    let mut total = 0.0;
+    let mut squared_total = 0.0;
+    for _i in 0..trials {
+        let result = simulate_round(game, &mut decisions, source);
+        total += result;
+        squared_total += result * result;
+    }
+    let n = trials as f64;
+    let mean = total / n;
+    let variance = f64::max(squared_total / n - mean * mean, 0.0) * n / f64::max(n - 1.0, 1.0);
+    (mean, f64::sqrt(variance / n))
+}
+
+// the card engine plays standard rules only, other rulesets return BlackjackError::UnsupportedRuleset
+pub fn try_cross_validate(game:&OptimizedBlackJackGame, trials:usize) -> Result<CrossValidation, BlackjackError> {
+    try_cross_validate_with(game, trials, &mut RngSource::new(rand::thread_rng()))
+}
+
+pub fn cross_validate(game:&OptimizedBlackJackGame, trials:usize) -> CrossValidation {
+    try_cross_validate(game, trials).unwrap_or_else(|e| panic!("{}", e))
+}
+
+// same as try_cross_validate with the simulation's cards from source
+pub fn try_cross_validate_with<S: CardSource>(game:&OptimizedBlackJackGame, trials:usize, source:&mut S) -> Result<CrossValidation, BlackjackError> {
+    let card_game = BlackJackGame::try_from(game)?;
+    let mut now = Instant::now();
+    let optimized_expected_value = game.try_get_expected_value()?;
+    let optimized_seconds = now.elapsed().as_secs_f64();
+
+    now = Instant::now();
+    let card_expected_value = card_game.try_get_expected_value()?;
+    let card_seconds = now.elapsed().as_secs_f64();
+
+    now = Instant::now();
+    let (simulated_expected_value, simulated_standard_error) = simulate_expected_value_with(game, trials, source);
+    let simulated_seconds = now.elapsed().as_secs_f64();
+
+    Ok(CrossValidation {
+        optimized_expected_value:optimized_expected_value,
+        optimized_seconds:optimized_seconds,
+        card_expected_value:card_expected_value,
+        card_seconds:card_seconds,
+        simulated_expected_value:simulated_expected_value,
+        simulated_standard_error:simulated_standard_error,
+        simulated_seconds:simulated_seconds,
+        trials:trials,
+    })
+}
+
+pub fn cross_validate_with<S: CardSource>(game:&OptimizedBlackJackGame, trials:usize, source:&mut S) -> CrossValidation {
+    try_cross_validate_with(game, trials, source).unwrap_or_else(|e| panic!("{}", e))
+}
@@ -0,0 +1,25 @@
+// fixtures shared by the integration tests, not every test file uses all of them
+#![allow(dead_code)]
+
+use blackjack_rs::cache_policy::CachePolicy;
+use blackjack_rs::card_source::RngSource;
+use blackjack_rs::opt_bj_game::OptimizedBlackJackGame;
+use blackjack_rs::validation::new_small_deck_game;
+
+use rand_chacha::ChaCha8Rng;
+
+// 16 cards, small enough for every engine to solve exactly in a test
+pub const SMALL_DECK_COUNTS:[usize;10] = [2, 1, 1, 1, 2, 2, 1, 1, 1, 4];
+
+// standard errors the simulation may be off by before it counts as a discrepancy
+pub const TOLERANCE:f64 = 5.0;
+
+// an undealt game on SMALL_DECK_COUNTS that never touches bin/
+pub fn small_deck_game() -> OptimizedBlackJackGame {
+    new_small_deck_game(&SMALL_DECK_COUNTS).with_cache_policy(CachePolicy::Never)
+}
+
+// deals come from here so every run sees the same positions
+pub fn seeded_source(seed:u64) -> RngSource<ChaCha8Rng> {
+    RngSource::seeded(seed)
+}
@@ -0,0 +1,52 @@
+use blackjack_rs::counting::CountingSystem;
+
+// every card of one standard deck by blackjack value index
+fn get_full_deck() -> Vec<usize> {
+    let mut cards = Vec::<usize>::new();
+    for i in 0usize..10usize {
+        let copies = if i == 9 { 16 } else { 4 };
+        cards.extend(std::iter::repeat(i).take(copies));
+    }
+    cards
+}
+
+#[test]
+fn balanced_systems_count_a_full_deck_to_zero() {
+    let deck = get_full_deck();
+    for system in CountingSystem::all() {
+        if system.is_balanced() {
+            assert_eq!(system.get_running_count(&deck), 0, "{}", system.get_name());
+            assert_eq!(system.get_initial_running_count(6), 0);
+        }
+    }
+    // KO counts the 7s, so a full deck comes to +4
+    assert_eq!(CountingSystem::Ko.get_running_count(&deck), 4);
+}
+
+#[test]
+fn hi_lo_running_and_true_counts() {
+    let hi_lo = CountingSystem::HiLo;
+    // 2 3 4 5 6 are +1, 7 8 9 are 0, tens and aces are -1
+    assert_eq!(hi_lo.get_running_count(&[1, 2, 3, 4, 5]), 5);
+    assert_eq!(hi_lo.get_running_count(&[6, 7, 8]), 0);
+    assert_eq!(hi_lo.get_running_count(&[0, 9, 9, 1]), -2);
+    // +6 with three decks left is a true count of +2
+    assert_eq!(hi_lo.get_true_count(6, 156), 2.0);
+    assert_eq!(hi_lo.get_true_count(-3, 26), -6.0);
+    assert_eq!(hi_lo.get_betting_count(6, 156), 2.0);
+    assert_eq!(CountingSystem::from_name("HiLo"), Some(CountingSystem::HiLo));
+}
+
+#[test]
+fn ko_starts_below_zero_and_bets_off_the_running_count() {
+    let ko = CountingSystem::Ko;
+    assert!(!ko.is_balanced());
+    assert_eq!(ko.get_initial_running_count(1), 0);
+    assert_eq!(ko.get_initial_running_count(6), -20);
+    assert_eq!(ko.get_initial_running_count(8), -28);
+    // a whole six deck shoe brings the count back up to the +4 pivot
+    let shoe = get_full_deck().repeat(6);
+    assert_eq!(ko.get_initial_running_count(6) + ko.get_running_count(&shoe), 4);
+    assert_eq!(ko.get_running_count(&[6]), 1);
+    assert_eq!(ko.get_betting_count(3, 100), 3.0);
+}
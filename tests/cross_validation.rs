@@ -0,0 +1,62 @@
+mod common;
+
+use blackjack_rs::error::BlackjackError;
+use blackjack_rs::game::BlackJackGame;
+use blackjack_rs::opt_bj_game::OptimizedBlackJackGame;
+use blackjack_rs::ruleset::Ruleset;
+use blackjack_rs::state::BlackjackState;
+use blackjack_rs::validation::{cross_validate_with, new_single_suit_game};
+
+use common::{seeded_source, small_deck_game, TOLERANCE};
+
+fn assert_cross_validates(game:&OptimizedBlackJackGame, trials:usize, seed:u64) {
+    let result = cross_validate_with(game, trials, &mut seeded_source(seed));
+    assert!(!result.has_discrepancy(TOLERANCE), "discrepancy for {}: {:?}", game.unique_key(), result);
+}
+
+#[test]
+fn single_suit_undealt() {
+    assert_cross_validates(&new_single_suit_game(), 20000, 1);
+}
+
+#[test]
+fn single_suit_dealt_positions() {
+    let game = new_single_suit_game();
+    let mut source = seeded_source(2);
+    for i in 0..5 {
+        assert_cross_validates(&game.deal_with(&mut source), 5000, 10 + i);
+    }
+}
+
+#[test]
+fn deck_with_cards_removed_undealt() {
+    assert_cross_validates(&small_deck_game(), 20000, 3);
+}
+
+#[test]
+fn conversions_keep_the_position() {
+    let mut source = seeded_source(4);
+    let game = small_deck_game().deal_with(&mut source).hit_with(&mut source);
+    let card_game = BlackJackGame::try_from(&game).unwrap();
+    let round_trip = OptimizedBlackJackGame::from(&card_game);
+    for state in [&game, &round_trip] {
+        assert_eq!(BlackjackState::get_hand_indices(state), card_game.get_hand_indices());
+        assert_eq!(BlackjackState::get_dealer_indices(state), card_game.get_dealer_indices());
+        assert_eq!(BlackjackState::get_deck_counts(state), card_game.get_deck_counts());
+        assert_eq!(BlackjackState::get_hand_value(state), BlackjackState::get_hand_value(&card_game));
+    }
+    // the card engine would play these as standard hit or stay
+    assert!(matches!(BlackJackGame::try_from(&game.clone().with_ruleset(Ruleset::Spanish21)), Err(BlackjackError::UnsupportedRuleset(_))));
+}
+
+#[test]
+fn dealer_table_matches_dealer_recursion() {
+    let game = small_deck_game();
+    let mut source = seeded_source(5);
+    for _i in 0..20 {
+        let position = game.deal_with(&mut source);
+        // the card engine still walks every dealer draw
+        let recursion_ev = BlackJackGame::try_from(&position).unwrap().get_stay_expected_value();
+        assert!((position.get_stay_expected_value() - recursion_ev).abs() < 1e-12, "stay ev mismatch for {}", position.unique_key());
+    }
+}
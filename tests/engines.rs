@@ -0,0 +1,175 @@
+mod common;
+
+use game_pieces_rs::card::{Card, Rank, Suit};
+
+use blackjack_rs::cache_policy::CachePolicy;
+use blackjack_rs::card_source::StackedSource;
+use blackjack_rs::error::BlackjackError;
+use blackjack_rs::game::BlackJackGame;
+use blackjack_rs::opt_bj_game::OptimizedBlackJackGame;
+use blackjack_rs::progress::CancellationToken;
+use blackjack_rs::ruleset::{Ruleset, Stake};
+use blackjack_rs::shoe::Shoe;
+use blackjack_rs::state::GameState;
+use blackjack_rs::validation::{new_single_suit_game, simulate_expected_value_with};
+
+use common::{seeded_source, small_deck_game, SMALL_DECK_COUNTS};
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+fn get_state(hand:&[usize], dealer:&[usize], stay:bool, deck_counts:[usize;10]) -> GameState {
+    GameState {
+        hand:hand.to_vec(),
+        dealer:dealer.to_vec(),
+        stay:stay,
+        deck_counts:deck_counts,
+        ruleset:Default::default(),
+        doubled:false,
+        stake:None,
+    }
+}
+
+// the value worked out by hand, from both engines
+fn assert_expected_value(state:GameState, expected:f64) {
+    let card_game = BlackJackGame::try_from(state.clone()).unwrap();
+    let game = OptimizedBlackJackGame::try_from(state).unwrap().with_cache_policy(CachePolicy::Never);
+    assert!((card_game.get_expected_value() - expected).abs() < 1e-12, "card engine: {}", card_game.get_expected_value());
+    assert!((game.get_expected_value() - expected).abs() < 1e-12, "optimized engine: {}", game.get_expected_value());
+}
+
+#[test]
+fn blackjack_pushes_a_dealer_blackjack() {
+    assert_expected_value(get_state(&[0, 9], &[0, 9], true, [0, 0, 0, 0, 1, 0, 0, 0, 0, 0]), 0.0);
+}
+
+#[test]
+fn blackjack_beats_a_drawn_dealer_21() {
+    // 6, then three 5s make the dealer a 4 card 21
+    assert_expected_value(get_state(&[0, 9], &[5], true, [0, 0, 0, 0, 3, 0, 0, 0, 0, 0]), 1.5);
+}
+
+#[test]
+fn stay_waits_for_a_dealer_below_17() {
+    // 14 against a dealer 15 that is ahead for now: a 5 makes the dealer 20, either ten busts them
+    assert_expected_value(get_state(&[9, 3], &[8, 5], true, [0, 0, 0, 0, 1, 0, 0, 0, 0, 2]), 1.0 / 3.0);
+}
+
+#[test]
+fn cache_policy_carries_to_child_positions() {
+    let mut source = seeded_source(21);
+    let position = small_deck_game().deal_with(&mut source).hit_with(&mut source).stay();
+    assert_eq!(position.get_cache_policy(), CachePolicy::Never);
+    assert_eq!(position.new_round().get_cache_policy(), CachePolicy::Never);
+    assert!(CachePolicy::MaxDepth(2).should_cache(Duration::ZERO, 1, 2));
+    assert!(!CachePolicy::SubtreeSize(100).should_cache(Duration::from_secs(60), 99, 0));
+}
+
+#[test]
+fn games_from_counts_and_removed_cards() {
+    assert!(matches!(OptimizedBlackJackGame::from_counts(&[0;10]), Err(BlackjackError::EmptyDeck)));
+    assert!(matches!(BlackJackGame::from_counts(&[0;10]), Err(BlackjackError::EmptyDeck)));
+    assert_eq!(OptimizedBlackJackGame::from_counts(&SMALL_DECK_COUNTS).unwrap().get_deck_counts(), SMALL_DECK_COUNTS);
+    assert_eq!(BlackJackGame::from_counts(&SMALL_DECK_COUNTS).unwrap().get_deck_counts(), SMALL_DECK_COUNTS);
+
+    let game = OptimizedBlackJackGame::from_standard_with_removed(1, &[0, 0, 9, 4]).unwrap();
+    assert_eq!(game.get_deck_counts(), [2, 4, 4, 4, 3, 4, 4, 4, 4, 15]);
+    assert!(matches!(OptimizedBlackJackGame::from_standard_with_removed(1, &[0, 0, 0, 0, 0]), Err(BlackjackError::CardNotAvailable { .. })));
+    assert!(matches!(OptimizedBlackJackGame::from_standard_with_removed(1, &[10]), Err(BlackjackError::InvalidCardIndex(10))));
+
+    let ace = Card::new(Rank::Ace, Suit::Spades);
+    let card_game = BlackJackGame::from_standard_with_removed(1, &[ace.clone()]).unwrap();
+    assert_eq!(card_game.get_deck_count(), 51);
+    assert!(matches!(BlackJackGame::from_standard_with_removed(1, &[ace.clone(), ace]), Err(BlackjackError::CardNotAvailable { .. })));
+}
+
+#[test]
+fn builder_checks_the_position() {
+    let game = OptimizedBlackJackGame::builder().hand(&[9, 5]).dealer(&[9]).standard_shoe(1).build().unwrap();
+    assert_eq!(game.get_hand(), &vec![9, 5]);
+    assert_eq!(game.get_deck_counts(), [4, 4, 4, 4, 4, 3, 4, 4, 4, 14]);
+
+    let remaining = OptimizedBlackJackGame::builder().hand(&[9, 5]).dealer(&[9]).remaining_counts(game.get_deck_counts()).build().unwrap();
+    assert_eq!(remaining.unique_key(), game.unique_key());
+
+    let no_aces = [0, 4, 4, 4, 4, 4, 4, 4, 4, 16];
+    assert!(matches!(OptimizedBlackJackGame::builder().hand(&[0, 9]).dealer(&[5]).shoe_counts(no_aces).build(), Err(BlackjackError::CardNotAvailable { .. })));
+    assert!(matches!(OptimizedBlackJackGame::builder().hand(&[9, 5]).standard_shoe(1).build(), Err(BlackjackError::InvalidPosition(_))));
+    assert!(matches!(OptimizedBlackJackGame::builder().hand(&[9, 5]).dealer(&[9, 4]).standard_shoe(1).build(), Err(BlackjackError::InvalidPosition(_))));
+    assert!(matches!(OptimizedBlackJackGame::builder().hand(&[9, 5]).dealer(&[9, 7, 2]).stay(true).standard_shoe(1).build(), Err(BlackjackError::InvalidPosition(_))));
+    assert!(matches!(OptimizedBlackJackGame::builder().hand(&[9, 9, 9, 0]).dealer(&[9]).standard_shoe(1).build(), Err(BlackjackError::InvalidPosition(_))));
+    assert!(matches!(OptimizedBlackJackGame::builder().hand(&[9, 5]).dealer(&[9]).build(), Err(BlackjackError::InvalidPosition(_))));
+}
+
+#[test]
+fn seeded_and_stacked_draws_replay() {
+    let game = OptimizedBlackJackGame::new_standard(1);
+    let first = game.deal_with(&mut seeded_source(7)).hit_with(&mut seeded_source(8));
+    let second = game.deal_with(&mut seeded_source(7)).hit_with(&mut seeded_source(8));
+    assert_eq!(first.unique_key(), second.unique_key());
+
+    // player, player, dealer, then the hit
+    let mut stacked = StackedSource::new(&[9, 5, 9, 4]);
+    let position = game.deal_with(&mut stacked).hit_with(&mut stacked);
+    assert_eq!(position.get_hand(), &vec![9, 5, 4]);
+    assert_eq!(position.get_dealer(), &vec![9]);
+    assert!(matches!(position.try_hit_with(&mut stacked), Err(BlackjackError::StackExhausted)));
+    assert!(matches!(OptimizedBlackJackGame::from_counts(&[0, 1, 1, 1, 0, 0, 0, 0, 0, 0]).unwrap().try_deal_with(&mut StackedSource::new(&[0, 1, 2])), Err(BlackjackError::CardNotAvailable { .. })));
+
+    let small = new_single_suit_game();
+    assert_eq!(simulate_expected_value_with(&small, 500, &mut seeded_source(3)), simulate_expected_value_with(&small, 500, &mut seeded_source(3)));
+    let mut first_shoe = Shoe::new_cut_card(2, 0.75, 1).with_seed(11);
+    let mut second_shoe = Shoe::new_cut_card(2, 0.75, 1).with_seed(11);
+    for _i in 0..80 {
+        assert_eq!(first_shoe.draw(), second_shoe.draw());
+    }
+}
+
+#[test]
+fn card_engine_refuses_states_it_cant_play() {
+    let counts = [0, 0, 0, 0, 1, 0, 0, 0, 0, 0];
+    let spanish_21 = GameState { ruleset:Ruleset::Spanish21, ..get_state(&[9, 5], &[9], false, counts) };
+    let doubled = GameState { ruleset:Ruleset::FreeBet, doubled:true, ..get_state(&[1, 7, 4], &[5], false, counts) };
+    let split = GameState { stake:Some(Ruleset::FreeBet.get_split_stakes(Stake::SINGLE).1), ..get_state(&[7, 4], &[5], false, counts) };
+    for state in [spanish_21, doubled, split] {
+        assert!(matches!(BlackJackGame::try_from(state.clone()), Err(BlackjackError::UnsupportedRuleset(_))), "{:?}", state);
+        // the optimized engine still takes them
+        assert!(OptimizedBlackJackGame::try_from(state).is_ok());
+    }
+    assert!(BlackJackGame::try_from(get_state(&[9, 5], &[9], false, counts)).is_ok());
+}
+
+#[test]
+fn card_engine_errors_when_the_deck_runs_out() {
+    let short = BlackJackGame::from_counts(&[0, 0, 0, 0, 1, 1, 0, 0, 0, 0]).unwrap();
+    assert!(matches!(short.try_get_expected_value(), Err(BlackjackError::NotEnoughCards { needed:3, available:2 })));
+
+    // the deal takes the last three cards
+    let position = BlackJackGame::from_counts(&[0, 0, 0, 0, 1, 1, 1, 0, 0, 0]).unwrap().deal();
+    assert_eq!(position.get_deck_count(), 0);
+    assert!(matches!(position.try_get_hit_expected_value(), Err(BlackjackError::NotEnoughCards { needed:1, available:0 })));
+    assert!(matches!(position.try_get_stay_expected_value(), Err(BlackjackError::NotEnoughCards { .. })));
+    assert!(matches!(position.try_get_expected_value(), Err(BlackjackError::NotEnoughCards { .. })));
+
+    let game = BlackJackGame::try_from(&small_deck_game()).unwrap().deal();
+    assert!((game.try_get_expected_value().unwrap() - game.get_expected_value()).abs() < 1e-12);
+}
+
+#[test]
+fn optimized_engine_cancels_and_reports_progress() {
+    let cancellation = CancellationToken::new();
+    let game = small_deck_game().with_cancellation_token(cancellation.clone());
+    cancellation.cancel();
+    assert!(matches!(game.try_get_expected_value(), Err(BlackjackError::Cancelled)));
+    // the token carries to every position reached from the game
+    assert!(matches!(game.deal().try_get_hit_expected_value(), Err(BlackjackError::Cancelled)));
+    cancellation.reset();
+
+    let last = Mutex::new(None);
+    let expected_value = game.try_get_expected_value_with_progress(Duration::from_millis(1), |x| *last.lock().unwrap() = Some(*x)).unwrap();
+    assert!((expected_value - small_deck_game().get_expected_value()).abs() < 1e-12);
+    let progress = last.lock().unwrap().unwrap();
+    assert!(progress.total_branches > 0);
+    assert_eq!(progress.completed_branches, progress.total_branches);
+    assert!(progress.nodes_explored > 0);
+}
@@ -0,0 +1,22 @@
+mod common;
+
+use blackjack_rs::ffi::{bj_position_action_evs, bj_position_free, bj_position_from_counts, bj_position_new, BjAction, BjActionEvs, BjPosition, BjStatus};
+
+use common::{seeded_source, small_deck_game, SMALL_DECK_COUNTS};
+
+#[test]
+fn c_api_matches_engine() {
+    let position = small_deck_game().deal_with(&mut seeded_source(41));
+    let mut handle:*mut BjPosition = std::ptr::null_mut();
+    let mut action_evs = BjActionEvs { hit:0.0, stay:0.0, best_action:BjAction::Stay, best_expected_value:0.0, can_double:false, double_ev:0.0, can_split:false, split_ev:0.0 };
+    unsafe {
+        let status = bj_position_new(position.get_hand().as_ptr(), position.get_hand().len(), position.get_dealer().as_ptr(), position.get_dealer().len(), false, SMALL_DECK_COUNTS.as_ptr(), false, &mut handle);
+        assert_eq!(status, BjStatus::Ok);
+        assert_eq!(bj_position_action_evs(handle, &mut action_evs), BjStatus::Ok);
+        bj_position_free(handle);
+        assert_eq!(bj_position_from_counts([0usize;10].as_ptr(), &mut handle), BjStatus::EmptyDeck);
+        assert_eq!(bj_position_new(std::ptr::null(), 1, std::ptr::null(), 0, false, SMALL_DECK_COUNTS.as_ptr(), false, &mut handle), BjStatus::NullPointer);
+    }
+    assert!((action_evs.hit - position.get_hit_expected_value()).abs() < 1e-12);
+    assert!((action_evs.stay - position.get_stay_expected_value()).abs() < 1e-12);
+}
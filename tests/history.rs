@@ -0,0 +1,61 @@
+mod common;
+
+use blackjack_rs::action::{Action, ActionExpectedValues};
+use blackjack_rs::game::BlackJackGame;
+use blackjack_rs::history::{read_hand_histories, replay_hand_history, DecisionRecord, HandHistory, HandHistoryWriter};
+use blackjack_rs::opt_bj_game::OptimizedBlackJackGame;
+use blackjack_rs::parallel_solver::ParallelSolver;
+
+use common::SMALL_DECK_COUNTS;
+
+use std::env;
+use std::fs;
+
+#[test]
+fn hand_history_round_trips_and_replays() {
+    let path = env::temp_dir().join(format!("blackjack_rs_history_{}.jsonl", std::process::id()));
+    fs::remove_file(&path).ok();
+    let history = HandHistory {
+        round:1,
+        deck_counts:SMALL_DECK_COUNTS,
+        hand:vec![9, 5],
+        dealer:vec![9, 7],
+        decisions:vec![DecisionRecord {
+            hand:vec![9, 5],
+            action:Action::Stay,
+            recommended:Action::Hit,
+            hit_ev:0.0,
+            stay_ev:0.0,
+        }],
+        payout:-1.0,
+    };
+    let mut writer = HandHistoryWriter::append(&path).unwrap();
+    writer.write(&history).unwrap();
+    writer.write(&history).unwrap();
+    let histories = read_hand_histories(&path).unwrap();
+    assert_eq!(histories, vec![history.clone(), history.clone()]);
+    fs::remove_file(&path).ok();
+
+    let replayed = replay_hand_history(&history, &ParallelSolver::new(2).unwrap()).unwrap();
+    let position = OptimizedBlackJackGame::builder().hand(&[9, 5]).dealer(&[9]).shoe_counts(SMALL_DECK_COUNTS).build().unwrap();
+    assert_eq!(replayed.len(), 1);
+    assert!((replayed[0].action_evs.stay - position.get_stay_expected_value()).abs() < 1e-12);
+    assert!((replayed[0].ev_lost - position.get_action_expected_values().get_expected_value_lost(Action::Stay)).abs() < 1e-12);
+}
+
+#[test]
+fn game_states_serialise_to_the_stable_schema() {
+    let game = OptimizedBlackJackGame::builder().hand(&[9, 5]).dealer(&[9]).shoe_counts(SMALL_DECK_COUNTS).build().unwrap();
+    let json = serde_json::to_string(&game).unwrap();
+    assert_eq!(json, r#"{"hand":[9,5],"dealer":[9],"stay":false,"deck_counts":[2,1,1,1,2,1,1,1,1,2]}"#);
+    let round_trip:OptimizedBlackJackGame = serde_json::from_str(&json).unwrap();
+    assert_eq!(round_trip.unique_key(), game.unique_key());
+
+    let card_game:BlackJackGame = serde_json::from_str(&json).unwrap();
+    assert_eq!(serde_json::to_string(&card_game).unwrap(), json);
+    assert!(serde_json::from_str::<OptimizedBlackJackGame>(r#"{"hand":[10],"dealer":[9],"stay":false,"deck_counts":[1,1,1,1,1,1,1,1,1,4]}"#).is_err());
+
+    let action_evs = ActionExpectedValues { hit:0.25, stay:-0.5, double:None, split:None };
+    assert_eq!(serde_json::to_string(&action_evs).unwrap(), r#"{"hit":0.25,"stay":-0.5}"#);
+    assert_eq!(serde_json::to_string(&Action::Hit).unwrap(), r#""hit""#);
+}
@@ -0,0 +1,41 @@
+use blackjack_rs::dealer_table::{DEALER_22, DEALER_BLACKJACK, DEALER_BUST};
+use blackjack_rs::infinite_deck::InfiniteDeckEngine;
+
+// published infinite deck dealer bust probabilities when the dealer stays on all 17s and the
+// blackjack check isn't conditioned away, by upcard 2 through ace
+const BUST_PROBS:[(usize, f64);10] = [
+    (1, 0.353608),
+    (2, 0.373875),
+    (3, 0.394468),
+    (4, 0.416404),
+    (5, 0.423150),
+    (6, 0.262312),
+    (7, 0.244741),
+    (8, 0.228425),
+    (9, 0.212109),
+    (0, 0.115286),
+];
+
+#[test]
+fn dealer_bust_probabilities_match_published_values() {
+    let engine = InfiniteDeckEngine::new_standard();
+    for (upcard, published) in BUST_PROBS.iter() {
+        let outcome_probs = engine.get_dealer_outcome_probs(*upcard);
+        let bust = outcome_probs[DEALER_BUST] + outcome_probs[DEALER_22];
+        assert!((bust - published).abs() < 1e-6, "upcard {}: {} against {}", upcard, bust, published);
+        assert!((outcome_probs.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+    }
+    // a ten up turns into a blackjack with any ace
+    assert!((engine.get_dealer_outcome_probs(9)[DEALER_BLACKJACK] - 1.0 / 13.0).abs() < 1e-12);
+}
+
+#[test]
+fn stiff_hand_stay_ev_is_set_by_the_dealer_bust_rate() {
+    let engine = InfiniteDeckEngine::new_standard();
+    // 16 only wins when the dealer busts
+    let stay_ev = engine.get_stay_expected_value(&[9, 5], 5);
+    assert!((stay_ev - (2.0 * 0.423150 - 1.0)).abs() < 2e-6, "{}", stay_ev);
+    // and against a 6 standing beats hitting
+    assert!(engine.get_hit_expected_value(&[9, 5], 5) < stay_ev);
+    assert_eq!(engine.get_expected_value(&[9, 5], 5), stay_ev);
+}
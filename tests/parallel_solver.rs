@@ -0,0 +1,117 @@
+mod common;
+
+use blackjack_rs::error::BlackjackError;
+use blackjack_rs::ev_cache::{EvCache, MemoryCache};
+use blackjack_rs::parallel_solver::ParallelSolver;
+use blackjack_rs::progress::CancellationToken;
+
+use common::{seeded_source, small_deck_game};
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn parallel_solver_matches_engine() {
+    let game = small_deck_game();
+    let solver = ParallelSolver::new(4).unwrap();
+    assert!((solver.get_expected_value(&game) - game.get_expected_value()).abs() < 1e-12);
+    let mut source = seeded_source(31);
+    for _i in 0..10 {
+        let position = game.deal_with(&mut source);
+        assert!((solver.get_expected_value(&position) - position.get_expected_value()).abs() < 1e-12, "ev mismatch for {}", position.unique_key());
+    }
+}
+
+#[test]
+fn parallel_solver_cancels_and_reports_progress() {
+    let game = small_deck_game();
+    let cancellation = CancellationToken::new();
+    let solver = ParallelSolver::new(2).unwrap().with_cancellation_token(cancellation.clone());
+    cancellation.cancel();
+    assert!(matches!(solver.try_get_expected_value(&game), Err(BlackjackError::Cancelled)));
+    cancellation.reset();
+    solver.try_get_expected_value(&game).unwrap();
+    let progress = solver.get_progress(Duration::from_secs(1));
+    assert_eq!(progress.completed_branches, progress.total_branches);
+    assert!(progress.nodes_explored > 0);
+}
+
+#[test]
+fn parallel_solver_resumes_from_checkpoint() {
+    let path = env::temp_dir().join(format!("blackjack_rs_checkpoint_{}.txt", std::process::id()));
+    fs::remove_file(&path).ok();
+    let game = small_deck_game();
+    let solver = ParallelSolver::new(2).unwrap().with_checkpoint(&path, Duration::from_millis(10));
+    let expected_value = solver.get_expected_value(&game);
+
+    // a half written line from a killed run
+    let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(b"v2[c=12cbji=[2-1").unwrap();
+    drop(file);
+
+    let resumed = ParallelSolver::new(2).unwrap();
+    // two threads can finish the same position, so the file may hold a few duplicates
+    assert!(resumed.resume_from_checkpoint(&path).unwrap() >= solver.get_memo_len());
+    assert_eq!(resumed.get_memo_len(), solver.get_memo_len());
+    assert_eq!(resumed.get_expected_value(&game), expected_value);
+    assert_eq!(resumed.get_progress(Duration::from_secs(1)).memo_hits, resumed.get_progress(Duration::from_secs(1)).memo_lookups);
+    fs::remove_file(&path).ok();
+}
+
+#[test]
+fn failed_checkpoint_writes_keep_reporting_and_fail_the_query() {
+    let path = env::temp_dir().join(format!("blackjack_rs_missing_{}", std::process::id())).join("checkpoint.txt");
+    let reports = Arc::new(AtomicUsize::new(0));
+    let counted = reports.clone();
+    let solver = ParallelSolver::new(2).unwrap()
+        .with_checkpoint(&path, Duration::from_millis(1))
+        .with_progress_callback(Duration::from_millis(1), move |_| { counted.fetch_add(1, Ordering::Relaxed); });
+    assert!(matches!(solver.try_get_expected_value(&small_deck_game()), Err(BlackjackError::CheckpointIo { .. })));
+    // the final report still comes after the last write fails
+    assert!(reports.load(Ordering::Relaxed) >= 1);
+}
+
+#[test]
+fn query_tokens_cancel_one_query_and_evs_are_shared() {
+    let position = small_deck_game().deal_with(&mut seeded_source(32));
+    let solver = ParallelSolver::new(2).unwrap();
+    let query_token = CancellationToken::new();
+    query_token.cancel();
+    assert!(matches!(solver.try_get_action_expected_values_with_token(&position, &query_token), Err(BlackjackError::Cancelled)));
+    // the solver's own token is untouched, so the next query runs
+    let action_evs = solver.try_get_action_expected_values_with_token(&position, &CancellationToken::new()).unwrap();
+    assert!((action_evs.hit - position.get_hit_expected_value()).abs() < 1e-12);
+    assert!((action_evs.stay - position.get_stay_expected_value()).abs() < 1e-12);
+
+    let cache = MemoryCache::new();
+    assert_eq!(cache.get(&position.unique_key()).unwrap(), None);
+    cache.insert(&position.unique_key(), action_evs).unwrap();
+    assert_eq!(cache.get(&position.unique_key()).unwrap(), Some(action_evs));
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn memo_and_memory_cache_stay_bounded() {
+    let position = small_deck_game().deal_with(&mut seeded_source(33));
+    let solver = ParallelSolver::new(2).unwrap().with_max_memo_len(16);
+    assert_eq!(solver.get_max_memo_len(), 16);
+    // emptying the memo part way through changes nothing but the time taken
+    assert!((solver.get_expected_value(&position) - position.get_expected_value()).abs() < 1e-12);
+    assert!(solver.get_memo_len() <= 16);
+
+    let cache = MemoryCache::with_capacity(2);
+    let action_evs = solver.try_get_action_expected_values_with_token(&position, &CancellationToken::new()).unwrap();
+    cache.insert("a", action_evs).unwrap();
+    cache.insert("b", action_evs).unwrap();
+    // overwriting a key already held doesn't empty it
+    cache.insert("b", action_evs).unwrap();
+    assert_eq!(cache.len(), 2);
+    cache.insert("c", action_evs).unwrap();
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.get("a").unwrap(), None);
+    assert_eq!(cache.get("c").unwrap(), Some(action_evs));
+}
@@ -0,0 +1,16 @@
+use blackjack_rs::penetration::analyse_penetration;
+use blackjack_rs::table::Strategy;
+
+#[test]
+fn deeper_penetration_deals_more_rounds_per_shoe() {
+    let analysis = analyse_penetration(1, 0, &[0.5, 0.9], Strategy::StayOn(17), 2000);
+    assert_eq!(analysis.results.len(), 2);
+    let (shallow, deep) = (analysis.results[0], analysis.results[1]);
+    assert_eq!((shallow.penetration, deep.penetration), (0.5, 0.9));
+    // 26 cards against 47, a round uses around five cards
+    assert!(shallow.rounds_per_shoe > 3.0 && shallow.rounds_per_shoe < 8.0, "{}", shallow.rounds_per_shoe);
+    assert!(deep.rounds_per_shoe > shallow.rounds_per_shoe + 2.0, "{} against {}", deep.rounds_per_shoe, shallow.rounds_per_shoe);
+    // staying on 17 loses, whichever way the shoe is dealt
+    assert!(analysis.continuous_expected_value < 0.0);
+    assert!(analysis.continuous_standard_error > 0.0);
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use blackjack_rs::chart::StrategyChart;
+use blackjack_rs::dealer_table::{DealerOutcomeTable, DEALER_22, DEALER_BLACKJACK, DEALER_BUST};
+use blackjack_rs::error::BlackjackError;
+use blackjack_rs::opt_bj_game::{new_deck_from_counts, OptimizedBlackJackGame};
+use blackjack_rs::parallel_solver::ParallelSolver;
+use blackjack_rs::ruleset::{Ruleset, Stake};
+use blackjack_rs::validation::simulate_expected_value_with;
+
+use common::{seeded_source, small_deck_game, SMALL_DECK_COUNTS, TOLERANCE};
+
+#[test]
+fn spanish_21_pays_bonuses_and_solves_doubles() {
+    assert_eq!(OptimizedBlackJackGame::new_with_ruleset(Ruleset::Spanish21, 2).get_deck_counts(), [8, 8, 8, 8, 8, 8, 8, 8, 8, 24]);
+    assert_eq!("spanish21".parse::<Ruleset>().unwrap(), Ruleset::Spanish21);
+    let rules = Ruleset::Spanish21;
+    // a player blackjack beats a dealer blackjack and player 21 beats a dealer 21
+    assert_eq!(rules.get_stay_payout(&[0, 9], Stake::SINGLE, DEALER_BLACKJACK), 1.5);
+    assert_eq!(rules.get_stay_payout(&[9, 8, 1], Stake::SINGLE, 4), 1.0);
+    assert_eq!(Ruleset::Standard.get_stay_payout(&[9, 8, 1], Stake::SINGLE, 4), 0.0);
+    // 5 card 21 and 6-7-8 bonuses, not paid on a double
+    assert_eq!(rules.get_stay_payout(&[1, 2, 3, 4, 6], Stake::SINGLE, 1), 1.5);
+    assert_eq!(rules.get_stay_payout(&[5, 6, 7], Stake::SINGLE, DEALER_BUST), 1.5);
+    assert_eq!(rules.get_stay_payout(&[5, 6, 7], Stake::DOUBLED, DEALER_BUST), 2.0);
+    assert_eq!(rules.get_stay_payout(&[9, 6], Stake::DOUBLED, 0), -2.0);
+
+    let game = small_deck_game().with_ruleset(rules);
+    let mut source = seeded_source(51);
+    for _i in 0..10 {
+        let position = game.deal_with(&mut source);
+        let action_evs = position.get_action_expected_values();
+        assert!((position.get_expected_value() - action_evs.get_best_expected_value()).abs() < 1e-12);
+        // a dealt blackjack can't double
+        if action_evs.double.is_none() {
+            assert!(position.is_hand_blackjack());
+            continue;
+        }
+        let doubled = position.double();
+        assert!(doubled.is_doubled());
+        assert!(matches!(doubled.try_hit(), Err(BlackjackError::ActionNotAllowed(_))));
+        // rescue caps the loss of a hand that isn't bust at the original bet
+        if !doubled.is_hand_bust() {
+            assert!(doubled.get_expected_value() >= -1.0);
+        }
+    }
+    let standard = small_deck_game().deal_with(&mut source);
+    assert_eq!(standard.get_double_expected_value(), None);
+    assert!(matches!(standard.try_double(), Err(BlackjackError::ActionNotAllowed(_))));
+    assert!(matches!(ParallelSolver::new(1).unwrap().try_get_expected_value(&game), Err(BlackjackError::UnsupportedRuleset(_))));
+}
+
+#[test]
+fn spanish_21_rescues_a_doubled_hand_that_can_only_lose() {
+    assert_eq!(Ruleset::Spanish21.get_rescue_payout(Stake::DOUBLED), Some(-1.0));
+    assert_eq!(Ruleset::Spanish21.get_rescue_payout(Stake::SINGLE), None);
+    assert_eq!(Ruleset::Standard.get_rescue_payout(Stake::DOUBLED), None);
+
+    // doubled on 11 and caught an ace for 12. The dealer's 10 draws one of the four 7s, so
+    // staying loses the doubled bet and rescue only gives up the original one
+    let position = OptimizedBlackJackGame::builder()
+        .hand(&[1, 8, 0])
+        .dealer(&[9])
+        .remaining_counts([0, 0, 0, 0, 0, 0, 4, 0, 0, 0])
+        .ruleset(Ruleset::Spanish21)
+        .doubled(true)
+        .build()
+        .unwrap();
+    assert_eq!(position.get_rescue_expected_value(), Some(-1.0));
+    assert_eq!(position.get_stay_expected_value(), -2.0);
+    assert_eq!(position.get_expected_value(), -1.0);
+    assert_eq!(position.stay().get_rescue_expected_value(), None);
+}
+
+#[test]
+fn free_bet_tracks_free_stakes_and_solves_splits() {
+    assert_eq!("free_bet".parse::<Ruleset>().unwrap(), Ruleset::FreeBet);
+    let free_double = Stake::SINGLE.get_doubled(true);
+    assert_eq!((free_double.at_risk, free_double.free), (1, 1));
+    assert_eq!(free_double.get_payout(1.0), 2.0);
+    assert_eq!(free_double.get_payout(-1.0), -1.0);
+    let rules = Ruleset::FreeBet;
+    assert!(rules.is_free_double(&[4, 5]));
+    assert!(!rules.is_free_double(&[0, 7]));
+    assert!(!rules.is_free_double(&[9, 1]));
+    assert!(rules.can_split(&[7, 7]) && !rules.can_split(&[9, 9]));
+    assert_eq!(rules.get_stay_payout(&[9, 9], free_double, 2), 2.0);
+    assert_eq!(rules.get_stay_payout(&[9, 9], free_double, DEALER_BLACKJACK), -1.0);
+    assert_eq!(rules.get_stay_payout(&[9, 9], Stake::SINGLE, DEALER_22), 0.0);
+    assert_eq!(rules.get_stay_payout(&[0, 9], Stake::SINGLE, DEALER_22), 1.5);
+    // the second hand of a free split costs nothing to lose, and neither hand can make a blackjack
+    let (first, second) = rules.get_split_stakes(Stake::SINGLE);
+    assert_eq!(rules.get_stay_payout(&[0, 9], first, 3), 1.0);
+    assert_eq!(rules.get_stay_payout(&[9, 7], second, 3), 0.0);
+    assert_eq!(rules.get_stay_payout(&[9, 9], second, 2), 1.0);
+
+    let position = OptimizedBlackJackGame::builder()
+        .hand(&[7, 7])
+        .dealer(&[5])
+        .remaining_counts(SMALL_DECK_COUNTS)
+        .ruleset(rules)
+        .build()
+        .unwrap();
+    let action_evs = position.get_action_expected_values();
+    assert!(action_evs.split.is_some() && action_evs.double.is_some());
+    assert!((position.get_expected_value() - action_evs.get_best_expected_value()).abs() < 1e-12);
+    let (first, second) = position.split();
+    assert_eq!((first.get_hand()[0], second.get_hand()[0]), (7, 7));
+    assert_eq!((second.get_stake().at_risk, second.get_stake().free), (0, 1));
+    assert_eq!(first.get_split_expected_value(), None);
+    assert_ne!(first.unique_key(), first.clone().with_ruleset(Ruleset::Standard).unique_key());
+
+    let free_doubled = OptimizedBlackJackGame::builder()
+        .hand(&[1, 7])
+        .dealer(&[5])
+        .remaining_counts(SMALL_DECK_COUNTS)
+        .ruleset(rules)
+        .build()
+        .unwrap()
+        .double();
+    assert_eq!(free_doubled.get_stake(), free_double);
+    assert!(matches!(ParallelSolver::new(1).unwrap().try_get_expected_value(&position), Err(BlackjackError::UnsupportedRuleset(_))));
+    assert_eq!(StrategyChart::build_for(rules, |_, _| None).rows.len(), StrategyChart::build(|_, _| None).rows.len() + 9);
+}
+
+#[test]
+fn double_exposure_solves_two_dealer_cards() {
+    assert_eq!("double_exposure".parse::<Ruleset>().unwrap(), Ruleset::DoubleExposure);
+    let rules = Ruleset::DoubleExposure;
+    // ties lose, a player blackjack wins them and is paid even money
+    assert_eq!(rules.get_stay_payout(&[9, 9], Stake::SINGLE, 3), -1.0);
+    assert_eq!(rules.get_stay_payout(&[9, 8], Stake::SINGLE, 0), -1.0);
+    assert_eq!(rules.get_stay_payout(&[9, 9], Stake::SINGLE, 2), 1.0);
+    assert_eq!(rules.get_stay_payout(&[9, 9], Stake::SINGLE, DEALER_22), 1.0);
+    assert_eq!(rules.get_stay_payout(&[0, 9], Stake::SINGLE, DEALER_BLACKJACK), 1.0);
+    assert_eq!(rules.get_stay_payout(&[0, 9], Stake::SINGLE, 4), 1.0);
+
+    let game = small_deck_game().with_ruleset(rules);
+    let dealt = game.deal_with(&mut seeded_source(52));
+    assert_eq!((dealt.get_hand().len(), dealt.get_dealer().len(), dealt.get_deck_count()), (2, 2, 12));
+
+    let builder = OptimizedBlackJackGame::builder().hand(&[9, 5]).dealer(&[9, 5]).remaining_counts(SMALL_DECK_COUNTS);
+    assert!(matches!(builder.build(), Err(BlackjackError::InvalidPosition(_))));
+    let position = OptimizedBlackJackGame::builder()
+        .hand(&[9, 5])
+        .dealer(&[9, 5])
+        .remaining_counts(SMALL_DECK_COUNTS)
+        .ruleset(rules)
+        .build()
+        .unwrap();
+    let action_evs = position.get_action_expected_values();
+    assert!((position.get_expected_value() - action_evs.get_best_expected_value()).abs() < 1e-12);
+    let outcome_probs = DealerOutcomeTable::shared().get_outcome_probs(&[9, 5], &new_deck_from_counts(&SMALL_DECK_COUNTS));
+    assert!((action_evs.stay - rules.get_stay_expected_value(&[9, 5], Stake::SINGLE, &outcome_probs)).abs() < 1e-12);
+
+    // the solver deals both dealer cards before the player's, the same as the simulation
+    let (mean, standard_error) = simulate_expected_value_with(&game, 5000, &mut seeded_source(7));
+    assert!((mean - game.get_expected_value()).abs() <= TOLERANCE * standard_error);
+
+    let chart = StrategyChart::build_for(rules, |_, _| None);
+    assert_eq!(chart.columns.len(), 26);
+    assert_eq!(chart.columns[0].dealer, vec![1, 1]);
+    assert_eq!(StrategyChart::build(|_, _| None).columns.len(), 10);
+}
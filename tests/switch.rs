@@ -0,0 +1,97 @@
+mod common;
+
+use blackjack_rs::dealer_table::{DealerOutcomeTable, DEALER_22, DEALER_BUST};
+use blackjack_rs::opt_bj_game::new_deck_from_counts;
+use blackjack_rs::ruleset::{Ruleset, Stake};
+use blackjack_rs::switch::SwitchHands;
+
+use common::{small_deck_game, SMALL_DECK_COUNTS};
+
+fn get_hands(first:&[usize], second:&[usize], upcard:usize) -> SwitchHands {
+    let mut remaining_counts = SMALL_DECK_COUNTS;
+    for card in first.iter().chain(second.iter()).chain([upcard].iter()) {
+        remaining_counts[*card] -= 1;
+    }
+    SwitchHands {
+        first:first.to_vec(),
+        second:second.to_vec(),
+        upcard:upcard,
+        remaining_counts:remaining_counts,
+    }
+}
+
+#[test]
+fn switch_payouts() {
+    let rules = Ruleset::Switch;
+    // blackjack pays 1:1 and isn't pushed by a dealer 22
+    assert_eq!(rules.get_stay_payout(&[0, 9], Stake::SINGLE, 3), 1.0);
+    assert_eq!(rules.get_stay_payout(&[0, 9], Stake::SINGLE, DEALER_22), 1.0);
+    assert_eq!(rules.get_stay_payout(&[9, 9], Stake::SINGLE, DEALER_22), 0.0);
+    assert_eq!(rules.get_stay_payout(&[9, 9], Stake::SINGLE, DEALER_BUST), 1.0);
+    assert_eq!(Ruleset::Standard.get_stay_payout(&[9, 9], Stake::SINGLE, DEALER_22), 1.0);
+    assert_eq!(rules.get_stay_payout(&[9, 0, 9], Stake::DOUBLED, DEALER_22), 0.0);
+    assert_eq!(rules.get_stay_payout(&[9, 0, 9], Stake::DOUBLED, 1), 2.0);
+}
+
+#[test]
+fn dealer_22_is_split_out_of_bust() {
+    // a dealer 12 busts on exactly 22 with a ten
+    let outcome_probs = DealerOutcomeTable::shared().get_outcome_probs(&[9, 1], &new_deck_from_counts(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 4]));
+    assert_eq!(outcome_probs[DEALER_22], 1.0);
+    assert_eq!(outcome_probs[DEALER_BUST], 0.0);
+}
+
+#[test]
+fn switched_swaps_the_second_cards() {
+    let hands = get_hands(&[0, 5], &[9, 9], 8);
+    let switched = hands.switched();
+    assert_eq!((switched.first.clone(), switched.second.clone()), (vec![0, 9], vec![9, 5]));
+    assert_eq!(switched.upcard, hands.upcard);
+    assert_eq!(switched.remaining_counts, hands.remaining_counts);
+    assert_eq!(switched.switched(), hands);
+}
+
+#[test]
+fn joint_evs_add_up_each_hand() {
+    let hands = get_hands(&[0, 5], &[9, 9], 8);
+    let switch_evs = hands.get_approximate_switch_expected_values();
+    let (first, second) = hands.try_get_positions().unwrap();
+    assert_eq!(first.get_ruleset(), Ruleset::Switch);
+    assert!((switch_evs.keep - (first.get_expected_value() + second.get_expected_value())).abs() < 1e-12);
+    let (first, second) = hands.switched().try_get_positions().unwrap();
+    assert!((switch_evs.switch - (first.get_expected_value() + second.get_expected_value())).abs() < 1e-12);
+
+    // either hand may double on two cards, so the joint evs count the doubles too
+    let rules = Ruleset::Switch;
+    assert!(rules.can_double(&[0, 5]) && rules.can_double(&[9, 9]));
+    assert!(!rules.can_double(&[0, 9]) && !rules.can_double(&[9, 5, 1]));
+    let (keep_first, keep_second) = hands.try_get_action_expected_values().unwrap();
+    assert!(keep_first.double.is_some() && keep_second.double.is_some());
+    let doubled = hands.try_get_positions().unwrap().0.double();
+    assert_eq!(doubled.get_stake(), Stake::DOUBLED);
+}
+
+#[test]
+fn switching_into_two_blackjacks() {
+    // a dealer 5 can't make blackjack, so two blackjacks paying 1:1 are worth exactly 2
+    let hands = get_hands(&[0, 0], &[9, 9], 4);
+    let switch_evs = hands.get_approximate_switch_expected_values();
+    assert!((switch_evs.switch - 2.0).abs() < 1e-9, "switch ev {}", switch_evs.switch);
+    assert!(switch_evs.keep < 2.0, "keep ev {}", switch_evs.keep);
+    assert!(switch_evs.should_switch());
+    assert_eq!(switch_evs.get_best_expected_value(), switch_evs.switch);
+
+    // and the same hands dealt as blackjacks stay put
+    let switch_evs = hands.switched().get_approximate_switch_expected_values();
+    assert!(!switch_evs.should_switch());
+    assert_eq!(switch_evs.get_best_expected_value(), switch_evs.keep);
+}
+
+#[test]
+fn deal_takes_five_cards() {
+    let dealt = SwitchHands::deal(&small_deck_game());
+    assert_eq!(dealt.first.len(), 2);
+    assert_eq!(dealt.second.len(), 2);
+    assert_eq!(dealt.remaining_counts.iter().sum::<usize>(), 11);
+    assert!(SwitchHands::try_deal(&small_deck_game().deal()).is_err());
+}
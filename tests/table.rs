@@ -0,0 +1,26 @@
+use blackjack_rs::card_source::StackedSource;
+use blackjack_rs::shoe::Shoe;
+use blackjack_rs::table::{BetPolicy, Seat, Strategy, Table};
+
+#[test]
+fn stacked_round_pays_each_seat() {
+    // first base a 10, second base an ace, dealer a 6, then a 7 and a 10 to the seats.
+    // The dealer draws 10 then 5 for a three card 21
+    let shoe = Shoe::new_cut_card(1, 0.75, 0).with_card_source(Box::new(StackedSource::new(&[9, 0, 5, 6, 9, 9, 4])));
+    let seat = Seat::new(Strategy::StayOn(17), BetPolicy::Flat(1.0));
+    let mut table = Table::with_shoe(shoe, vec![seat; 2]);
+    table.play_round();
+    let results = table.get_seat_results();
+    assert_eq!(table.get_rounds(), 1);
+    // 17 loses to 21, the blackjack beats a drawn 21 and pays 3 to 2
+    assert_eq!(results[0].net_result, -1.0);
+    assert_eq!(results[1].net_result, 1.5);
+    assert_eq!(results[1].total_bet, 1.0);
+    assert_eq!(table.get_shoe().get_dealt(), 7);
+}
+
+#[test]
+#[should_panic]
+fn table_needs_a_seat() {
+    Table::new(1, 0.75, Vec::<Seat>::new());
+}
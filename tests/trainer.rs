@@ -0,0 +1,21 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+fn trainer_scores_a_seeded_session() {
+    let mut trainer = Command::new(env!("CARGO_BIN_EXE_trainer"))
+        .args(["--seed", "1", "--decks", "1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // stay on the first hand, then quit at the next decision
+    trainer.stdin.take().unwrap().write_all(b"s\nq\n").unwrap();
+    let output = trainer.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("seed 1 (replay with --seed 1)"), "{}", stdout);
+    assert!(stdout.contains("Stay EV"), "{}", stdout);
+    assert!(stdout.contains("Rounds: 1,"), "{}", stdout);
+    assert!(stdout.contains("Decisions: 1,"), "{}", stdout);
+}